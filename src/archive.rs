@@ -0,0 +1,419 @@
+use crate::compression::{self, read_limited, Compression, Limited};
+use crate::utils::render_path;
+use crate::R;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Tar(Option<Compression>),
+    Zip,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(Format::Zip)
+        } else if name.ends_with(".tgz") {
+            Some(Format::Tar(Some(Compression::Gzip)))
+        } else if name.ends_with(".tar") {
+            Some(Format::Tar(None))
+        } else {
+            let compression = Compression::from_path(path)?;
+            Format::from_path(&compression::inner_path(path))
+                .filter(|format| *format == Format::Tar(None))
+                .map(|_| Format::Tar(Some(compression)))
+        }
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self != Format::Tar(None)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug)]
+pub struct Member {
+    /// Relative path inside the archive, without leading `./` or trailing
+    /// slashes.
+    pub path: PathBuf,
+    pub kind: Kind,
+    pub size: u64,
+    /// Only known for formats that compress members individually, i.e. zip.
+    pub compressed_size: Option<u64>,
+}
+
+/// Splits paths like `release.tar.gz//bin/tool.conf` into the archive and
+/// the path of a member inside it, at the first `//` that follows an
+/// archive. A trailing `//` refers to the archive root.
+pub fn split_path(path: &Path) -> Option<(PathBuf, Format, PathBuf)> {
+    let string = path.to_str()?;
+    string.match_indices("//").find_map(|(index, _)| {
+        let archive = PathBuf::from(&string[..index]);
+        let format = Format::from_path(&archive)?;
+        let member = &string[index + 2..];
+        Some((
+            archive,
+            format,
+            normalize(Path::new(member)).unwrap_or_default(),
+        ))
+    })
+}
+
+/// Reads the list of members of an archive without extracting anything.
+/// Directories that are only implied by the paths of their contents are
+/// added, and members are sorted by path.
+pub fn read(file: &Path, format: Format) -> R<Vec<Member>> {
+    let result = match format {
+        Format::Tar(compression) => open_tar(file, compression).and_then(read_tar),
+        Format::Zip => File::open(file).map_err(Into::into).and_then(read_zip),
+    };
+    let mut members = result
+        .map_err(|error| format!("cannot read archive {}: {}\n", render_path(file), error))?;
+    add_implied_directories(&mut members);
+    // In tar archives, later entries replace earlier ones with the same
+    // path. The sort is stable, so the last of them comes last.
+    members.sort_by(|a, b| a.path.cmp(&b.path));
+    members.dedup_by(|later, earlier| {
+        let duplicate = later.path == earlier.path;
+        if duplicate {
+            std::mem::swap(later, earlier);
+        }
+        duplicate
+    });
+    Ok(members)
+}
+
+/// Reads the contents of a single member into memory, up to
+/// `compression::LIMIT`.
+pub fn read_member(file: &Path, format: Format, member: &Path) -> R<Limited> {
+    let result = match format {
+        Format::Tar(compression) => {
+            open_tar(file, compression).and_then(|input| read_tar_member(input, member))
+        }
+        Format::Zip => File::open(file)
+            .map_err(Into::into)
+            .and_then(|input| read_zip_member(input, member)),
+    };
+    result
+        .map_err(|error| format!("cannot read archive {}: {}\n", render_path(file), error))?
+        .ok_or_else(|| {
+            format!(
+                "path not found: {}//{}\n",
+                render_path(file),
+                member.to_string_lossy()
+            )
+            .into()
+        })
+}
+
+fn open_tar(file: &Path, compression: Option<Compression>) -> R<Box<dyn Read>> {
+    let input = File::open(file)?;
+    match compression {
+        Some(compression) => compression.reader(input),
+        None => Ok(Box::new(input)),
+    }
+}
+
+fn read_tar(input: impl Read) -> R<Vec<Member>> {
+    let mut result = vec![];
+    for entry in tar::Archive::new(input).entries()? {
+        let entry = entry?;
+        let path = match normalize(&entry.path()?) {
+            Some(path) => path,
+            None => continue,
+        };
+        let entry_type = entry.header().entry_type();
+        let kind = if entry_type.is_dir() {
+            Kind::Directory
+        } else if entry_type.is_file() || entry_type.is_hard_link() {
+            Kind::File
+        } else if entry_type.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::Other
+        };
+        result.push(Member {
+            path,
+            kind,
+            size: if kind == Kind::Directory {
+                0
+            } else {
+                entry.size()
+            },
+            compressed_size: None,
+        });
+    }
+    Ok(result)
+}
+
+/// Reads the last entry with the given path, since that's the one that
+/// extracting the archive would leave behind.
+fn read_tar_member(input: impl Read, member: &Path) -> R<Option<Limited>> {
+    let mut result = None;
+    for entry in tar::Archive::new(input).entries()? {
+        let entry = entry?;
+        if normalize(&entry.path()?).as_deref() == Some(member) {
+            result = Some(read_limited(entry)?);
+        }
+    }
+    Ok(result)
+}
+
+fn read_zip(input: File) -> R<Vec<Member>> {
+    let mut archive = zip::ZipArchive::new(input)?;
+    let mut result = vec![];
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        let path = match file.enclosed_name().as_deref().and_then(normalize) {
+            Some(path) => path,
+            None => continue,
+        };
+        let kind = if file.is_dir() {
+            Kind::Directory
+        } else if file.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+        result.push(Member {
+            path,
+            kind,
+            size: file.size(),
+            compressed_size: Some(file.compressed_size()),
+        });
+    }
+    Ok(result)
+}
+
+fn read_zip_member(input: File, member: &Path) -> R<Option<Limited>> {
+    let mut archive = zip::ZipArchive::new(input)?;
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file
+            .enclosed_name()
+            .as_deref()
+            .and_then(normalize)
+            .as_deref()
+            == Some(member)
+        {
+            return Ok(Some(read_limited(file)?));
+        }
+    }
+    Ok(None)
+}
+
+/// Drops `.` components and anything that would point outside of the
+/// archive. Returns `None` for the archive root.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let result: PathBuf = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    if result.as_os_str().is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+fn add_implied_directories(members: &mut Vec<Member>) {
+    let mut known: HashSet<PathBuf> = members.iter().map(|member| member.path.clone()).collect();
+    let mut implied = vec![];
+    for member in members.iter() {
+        for ancestor in member.path.ancestors().skip(1) {
+            if ancestor.as_os_str().is_empty() || !known.insert(ancestor.to_path_buf()) {
+                continue;
+            }
+            implied.push(Member {
+                path: ancestor.to_path_buf(),
+                kind: Kind::Directory,
+                size: 0,
+                compressed_size: None,
+            });
+        }
+    }
+    members.append(&mut implied);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test::*;
+    use std::io::Write;
+
+    /// Writes a tar archive containing files with the given paths and
+    /// contents.
+    pub fn write_tar(path: &str, files: &[(&str, &str)]) -> R<()> {
+        let mut builder = tar::Builder::new(File::create(path)?);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, contents.as_bytes())?;
+        }
+        builder.finish()?;
+        Ok(())
+    }
+
+    pub fn write_zip(path: &str, files: &[(&str, &str)]) -> R<()> {
+        let mut writer = zip::ZipWriter::new(File::create(path)?);
+        for (name, contents) in files {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default())?;
+            writer.write_all(contents.as_bytes())?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn paths(members: &[Member]) -> Vec<(String, Kind)> {
+        members
+            .iter()
+            .map(|member| (member.path.to_string_lossy().into_owned(), member.kind))
+            .collect()
+    }
+
+    #[test]
+    fn detects_archive_formats_by_extension() {
+        assert_eq!(
+            Format::from_path(Path::new("a.tar")),
+            Some(Format::Tar(None))
+        );
+        assert_eq!(
+            Format::from_path(Path::new("a.tar.gz")),
+            Some(Format::Tar(Some(Compression::Gzip)))
+        );
+        assert_eq!(
+            Format::from_path(Path::new("a.TGZ")),
+            Some(Format::Tar(Some(Compression::Gzip)))
+        );
+        assert_eq!(
+            Format::from_path(Path::new("a.tar.zst")),
+            Some(Format::Tar(Some(Compression::Zstd)))
+        );
+        assert_eq!(Format::from_path(Path::new("a.jar")), Some(Format::Zip));
+        assert_eq!(Format::from_path(Path::new("a.gz")), None);
+        assert_eq!(Format::from_path(Path::new("a.zip.gz")), None);
+    }
+
+    #[test]
+    fn adds_implied_directories() -> R<()> {
+        let _setup = setup()?;
+        write_tar("foo.tar", &[("./a/b/c", "c"), ("d", "dd")])?;
+        assert_eq!(
+            paths(&read(Path::new("foo.tar"), Format::Tar(None))?),
+            vec![
+                ("a".to_string(), Kind::Directory),
+                ("a/b".to_string(), Kind::Directory),
+                ("a/b/c".to_string(), Kind::File),
+                ("d".to_string(), Kind::File),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn later_tar_entries_replace_earlier_ones() -> R<()> {
+        let _setup = setup()?;
+        write_tar("foo.tar", &[("a", "old"), ("b", ""), ("./a", "newer")])?;
+        let members = read(Path::new("foo.tar"), Format::Tar(None))?;
+        assert_eq!(
+            members
+                .iter()
+                .map(|member| (member.path.to_string_lossy().into_owned(), member.size))
+                .collect::<Vec<_>>(),
+            vec![("a".to_string(), 5), ("b".to_string(), 0)]
+        );
+        assert_eq!(
+            read_member(Path::new("foo.tar"), Format::Tar(None), Path::new("a"))?.bytes,
+            b"newer"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_compressed_sizes_of_zip_members() -> R<()> {
+        let _setup = setup()?;
+        write_zip("foo.zip", &[("foo", &"a".repeat(1000))])?;
+        let members = read(Path::new("foo.zip"), Format::Zip)?;
+        assert_eq!(members[0].size, 1000);
+        assert!(members[0].compressed_size.unwrap() < 100);
+        Ok(())
+    }
+
+    #[test]
+    fn splits_paths_into_archive_and_member() {
+        assert_eq!(
+            split_path(Path::new("dir/foo.tgz//bin/./tool.conf")),
+            Some((
+                PathBuf::from("dir/foo.tgz"),
+                Format::Tar(Some(Compression::Gzip)),
+                PathBuf::from("bin/tool.conf")
+            ))
+        );
+        assert_eq!(
+            split_path(Path::new("foo.zip//")),
+            Some((PathBuf::from("foo.zip"), Format::Zip, PathBuf::new()))
+        );
+        assert_eq!(
+            split_path(Path::new("dir//x.tar//member")),
+            Some((
+                PathBuf::from("dir//x.tar"),
+                Format::Tar(None),
+                PathBuf::from("member")
+            ))
+        );
+        assert_eq!(split_path(Path::new("dir//foo")), None);
+    }
+
+    #[test]
+    fn reads_single_members() -> R<()> {
+        let _setup = setup()?;
+        write_tar("foo.tar", &[("a", "a"), ("dir/b", "b")])?;
+        write_zip("foo.zip", &[("a", "a"), ("dir/b", "b")])?;
+        for (archive, format) in [("foo.tar", Format::Tar(None)), ("foo.zip", Format::Zip)] {
+            assert_eq!(
+                read_member(Path::new(archive), format, Path::new("dir/b"))?.bytes,
+                b"b"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn stops_reading_members_at_the_limit() -> R<()> {
+        let _setup = setup()?;
+        let big = "a".repeat(compression::LIMIT as usize * 4);
+        write_tar("foo.tar", &[("big", &big)])?;
+        write_zip("foo.zip", &[("big", &big)])?;
+        for (archive, format) in [("foo.tar", Format::Tar(None)), ("foo.zip", Format::Zip)] {
+            let member = read_member(Path::new(archive), format, Path::new("big"))?;
+            assert_eq!(member.bytes.len() as u64, compression::LIMIT);
+            assert!(member.truncated);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_corrupt_archives() -> R<()> {
+        let _setup = setup()?;
+        std::fs::write("foo.zip", "not a zip file")?;
+        let result = read(Path::new("foo.zip"), Format::Zip);
+        assert!(result
+            .map_err(|error| error.to_string())
+            .unwrap_err()
+            .starts_with("cannot read archive ./foo.zip: "));
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use crate::R;
+use glob::Pattern;
+use regex::Regex;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EntryType {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// A filter on entry names. Globs have to match the whole name, regexes
+/// match anywhere in it unless anchored with `^` and `$`.
+pub enum NamePattern {
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => pattern.matches(name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+pub struct Args {
+    pub path: PathBuf,
+    pub recursive_stats: bool,
+    pub sizes: bool,
+    pub sort_by_size: bool,
+    pub size_bars: bool,
+    pub include: Vec<NamePattern>,
+    pub exclude: Vec<NamePattern>,
+    pub entry_type: Option<EntryType>,
+    pub collapse: bool,
+    pub history: bool,
+    pub blame: bool,
+    pub pretty: bool,
+    pub raw: bool,
+    pub render: bool,
+    pub preview: bool,
+    pub show_whitespace: bool,
+    /// Passes escape sequences in file contents through to the terminal.
+    pub ansi: bool,
+    /// Overrides the detected encoding of text files.
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+    /// The second path given together with `--diff`.
+    pub diff: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> R<Args> {
+        let mut paths = vec![];
+        let mut diff = false;
+        let mut result = Args {
+            path: PathBuf::from("."),
+            recursive_stats: false,
+            sizes: false,
+            sort_by_size: false,
+            size_bars: false,
+            include: vec![],
+            exclude: vec![],
+            entry_type: None,
+            collapse: false,
+            history: false,
+            blame: false,
+            pretty: false,
+            raw: false,
+            render: false,
+            preview: false,
+            show_whitespace: false,
+            ansi: false,
+            encoding: None,
+            diff: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--recursive-stats" => result.recursive_stats = true,
+                "--sizes" => result.sizes = true,
+                "--sort-by-size" => result.sort_by_size = true,
+                "--size-bars" => result.size_bars = true,
+                "--include" => result.include.push(parse_glob(value(&mut args, &arg)?)?),
+                "--exclude" => result.exclude.push(parse_glob(value(&mut args, &arg)?)?),
+                "--include-regex" => result.include.push(parse_regex(value(&mut args, &arg)?)?),
+                "--exclude-regex" => result.exclude.push(parse_regex(value(&mut args, &arg)?)?),
+                "--collapse" => result.collapse = true,
+                "--history" => result.history = true,
+                "--blame" => result.blame = true,
+                "--pretty" => result.pretty = true,
+                "--raw" => result.raw = true,
+                "--render" => result.render = true,
+                "--preview" => result.preview = true,
+                "--show-whitespace" => result.show_whitespace = true,
+                "--ansi" => result.ansi = true,
+                "--diff" => diff = true,
+                "--encoding" => {
+                    let label = value(&mut args, &arg)?;
+                    result.encoding = Some(
+                        encoding_rs::Encoding::for_label(label.as_bytes())
+                            .ok_or_else(|| format!("unknown encoding: {}\n", label))?,
+                    );
+                }
+                "--type" => {
+                    result.entry_type = Some(match value(&mut args, &arg)?.as_str() {
+                        "f" => EntryType::File,
+                        "d" => EntryType::Directory,
+                        "l" => EntryType::Symlink,
+                        other => {
+                            return Err(format!(
+                                "invalid value for --type: {} (expected f, d or l)\n",
+                                other
+                            )
+                            .into())
+                        }
+                    })
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown flag: {}\n", flag).into())
+                }
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+        let mut paths = paths.into_iter();
+        if let Some(path) = paths.next() {
+            result.path = path;
+        }
+        if diff {
+            result.diff = Some(paths.next().ok_or("--diff needs two paths to compare\n")?);
+        }
+        if let Some(arg) = paths.next() {
+            return Err(format!("unexpected argument: {}\n", arg.to_string_lossy()).into());
+        }
+        Ok(result)
+    }
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> R<String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {}\n", flag).into())
+}
+
+fn parse_glob(pattern: String) -> R<NamePattern> {
+    Pattern::new(&pattern)
+        .map(NamePattern::Glob)
+        .map_err(|error| format!("invalid glob {}: {}\n", pattern, error).into())
+}
+
+fn parse_regex(pattern: String) -> R<NamePattern> {
+    Regex::new(&pattern)
+        .map(NamePattern::Regex)
+        .map_err(|error| format!("invalid regex {}: {}\n", pattern, error).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+
+    #[test]
+    fn unknown_flags_are_rejected() -> R<()> {
+        let mut setup = setup()?;
+        let result = setup.run(vec!["--foo"]);
+        assert_eq!(
+            result.map_err(|x| x.to_string()),
+            Err("unknown flag: --foo\n".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn flags_can_come_before_the_path() -> R<()> {
+        let args = Args::parse(
+            vec!["--recursive-stats", "foo"]
+                .into_iter()
+                .map(String::from),
+        )?;
+        assert_eq!(args.path, PathBuf::from("foo"));
+        assert!(args.recursive_stats);
+        Ok(())
+    }
+
+    #[test]
+    fn flags_with_values_can_be_given_multiple_times() -> R<()> {
+        let args = Args::parse(
+            vec!["--include", "*.rs", "--include", "*.toml", "--type", "f"]
+                .into_iter()
+                .map(String::from),
+        )?;
+        assert_eq!(args.include.len(), 2);
+        assert_eq!(args.entry_type, Some(EntryType::File));
+        assert_eq!(args.path, PathBuf::from("."));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_regexes_are_reported() -> R<()> {
+        let result = Args::parse(vec!["--include-regex", "("].into_iter().map(String::from));
+        assert!(result
+            .map(|_| ())
+            .map_err(|x| x.to_string())
+            .unwrap_err()
+            .starts_with("invalid regex (: "));
+        Ok(())
+    }
+
+    #[test]
+    fn diff_takes_a_second_path() -> R<()> {
+        let args = Args::parse(vec!["a", "--diff", "b"].into_iter().map(String::from))?;
+        assert_eq!(args.path, PathBuf::from("a"));
+        assert_eq!(args.diff, Some(PathBuf::from("b")));
+        Ok(())
+    }
+
+    #[test]
+    fn second_paths_are_rejected_without_diff() -> R<()> {
+        let result = Args::parse(vec!["a", "b"].into_iter().map(String::from));
+        assert_eq!(
+            result.map(|_| ()).map_err(|x| x.to_string()),
+            Err("unexpected argument: b\n".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_values_are_reported() -> R<()> {
+        let result = Args::parse(vec!["--include"].into_iter().map(String::from));
+        assert_eq!(
+            result.map(|_| ()).map_err(|x| x.to_string()),
+            Err("missing value for --include\n".to_string())
+        );
+        Ok(())
+    }
+}
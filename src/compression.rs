@@ -0,0 +1,191 @@
+use crate::R;
+use std::io::{self, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Decompressed files and archive members are only read up to this size, so
+/// that decompression bombs can't exhaust the memory.
+#[cfg(not(test))]
+pub const LIMIT: u64 = 64 * 1024 * 1024;
+#[cfg(test)]
+pub const LIMIT: u64 = 64 * 1024;
+
+/// Contents read into memory up to `LIMIT`.
+pub struct Limited {
+    pub bytes: Vec<u8>,
+    /// Whether there was more than `LIMIT`.
+    pub truncated: bool,
+}
+
+pub fn read_limited(input: impl Read) -> R<Limited> {
+    let mut bytes = vec![];
+    input.take(LIMIT + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() as u64 > LIMIT;
+    bytes.truncate(LIMIT as usize);
+    Ok(Limited { bytes, truncated })
+}
+
+/// Compression formats for single files, as opposed to archives.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path(path: &Path) -> Option<Compression> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Wraps `input` in a reader that yields the uncompressed bytes. xz has
+    /// no streaming decoder, so it gets decompressed into memory upfront,
+    /// stopping after one byte more than `LIMIT`. This also limits how much
+    /// of an xz compressed tar archive can be read.
+    pub fn reader<'a>(self, input: impl Read + 'a) -> R<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(input)),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(input)),
+            Compression::Xz => {
+                let mut output = LimitedWriter(vec![]);
+                match lzma_rs::xz_decompress(&mut BufReader::new(input), &mut output) {
+                    Err(_) if output.0.len() as u64 > LIMIT => {}
+                    result => result?,
+                }
+                Box::new(Cursor::new(output.0))
+            }
+            Compression::Zstd => Box::new(ruzstd::decoding::StreamingDecoder::new(input)?),
+        })
+    }
+
+    pub fn decompress(self, input: &[u8]) -> R<Limited> {
+        read_limited(self.reader(input)?)
+    }
+}
+
+/// Fails writes beyond one byte more than `LIMIT`.
+struct LimitedWriter(Vec<u8>);
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = (LIMIT + 1) as usize - self.0.len();
+        if room == 0 {
+            return Err(io::Error::other("output limit reached"));
+        }
+        let length = buf.len().min(room);
+        self.0.extend_from_slice(&buf[..length]);
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The path without the compression extension, e.g. `app.log` for
+/// `app.log.gz`.
+pub fn inner_path(path: &Path) -> PathBuf {
+    path.with_extension("")
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use std::io::Write;
+
+    pub fn compress(compression: Compression, input: &[u8]) -> R<Vec<u8>> {
+        Ok(match compression {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(input)?;
+                encoder.finish()?
+            }
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+                encoder.write_all(input)?;
+                encoder.finish()?
+            }
+            Compression::Xz => {
+                let mut output = vec![];
+                lzma_rs::xz_compress(&mut BufReader::new(input), &mut output)?;
+                output
+            }
+            Compression::Zstd => ruzstd::encoding::compress_to_vec(
+                input,
+                ruzstd::encoding::CompressionLevel::Fastest,
+            ),
+        })
+    }
+
+    #[test]
+    fn round_trips_all_formats() -> R<()> {
+        for compression in [
+            Compression::Gzip,
+            Compression::Bzip2,
+            Compression::Xz,
+            Compression::Zstd,
+        ] {
+            let compressed = compress(compression, b"foo\nbar\n")?;
+            assert_eq!(compression.decompress(&compressed)?.bytes, b"foo\nbar\n");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn stops_decompressing_at_the_limit() -> R<()> {
+        for compression in [
+            Compression::Gzip,
+            Compression::Bzip2,
+            Compression::Xz,
+            Compression::Zstd,
+        ] {
+            let compressed = compress(compression, &[b'a'; LIMIT as usize * 4])?;
+            let decompressed = compression.decompress(&compressed)?;
+            assert_eq!(decompressed.bytes.len() as u64, LIMIT);
+            assert!(decompressed.truncated);
+            let decompressed = compression.decompress(&compress(compression, b"foo")?)?;
+            assert!(!decompressed.truncated);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn detects_formats_by_extension() {
+        assert_eq!(
+            Compression::from_path(Path::new("app.log.gz")),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("a.ZST")),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_path(Path::new("a.zip")), None);
+        assert_eq!(
+            inner_path(Path::new("app.log.gz")),
+            PathBuf::from("app.log")
+        );
+    }
+
+    #[test]
+    fn reports_corrupt_input() {
+        assert!(Compression::Gzip.decompress(b"not gzip").is_err());
+    }
+}
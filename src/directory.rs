@@ -1,36 +1,140 @@
+pub mod diff;
+mod filter;
 mod stats;
 mod tree;
 
+use crate::archive;
+use crate::file::escapes;
+use crate::git;
+use crate::utils::{render_path, render_size};
 use crate::{write_separator, Context, R};
 use colored::*;
+use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn output(context: &mut Context, directory: PathBuf) -> R<()> {
+    let statuses = git::Statuses::read(&directory);
+    if let Some(statuses) = &statuses {
+        writeln!(
+            context.stdout,
+            "{}",
+            git::Summary::read(&directory, statuses)
+        )?;
+        write_separator(context)?;
+    }
     let children = read_directory(directory)?;
+    let tree = tree::Tree::read(context, &children)?;
     stats::output(context, &children)?;
     write_separator(context)?;
-    output_file_listing(context, &children)?;
+    output_file_listing(context, statuses.as_ref(), &children, &tree)?;
     write_separator(context)?;
-    tree::output(context, children)?;
+    let recursive_stats = tree::output(context, statuses.as_ref(), tree)?;
+    if context.args.recursive_stats {
+        write_separator(context)?;
+        writeln!(context.stdout, "{}", recursive_stats)?;
+    }
     Ok(())
 }
 
-fn output_file_listing(context: &mut Context, children: &[fs::DirEntry]) -> R<()> {
+/// Shows an archive like a directory, without extracting it. `root` is a
+/// directory inside the archive, or empty for the whole archive.
+pub fn output_archive(
+    context: &mut Context,
+    file: &Path,
+    format: archive::Format,
+    members: &[archive::Member],
+    root: &Path,
+) -> R<()> {
+    let size = fs::metadata(file)?.len();
+    let unpacked: u64 = members
+        .iter()
+        .filter(|member| member.path.starts_with(root))
+        .map(|member| member.size)
+        .sum();
+    if root.as_os_str().is_empty() {
+        write!(
+            context.stdout,
+            "archive: {}, {} bytes, {} unpacked",
+            render_path(file),
+            size,
+            render_size(unpacked)
+        )?;
+        if let Some(ratio) = (size * 100).checked_div(unpacked) {
+            if format.is_compressed() {
+                write!(context.stdout, ", compression ratio {}%", ratio)?;
+            }
+        }
+        writeln!(context.stdout)?;
+    } else {
+        writeln!(
+            context.stdout,
+            "archive directory: {}//{}, {} unpacked",
+            render_path(file),
+            root.to_string_lossy(),
+            render_size(unpacked)
+        )?;
+    }
+    write_separator(context)?;
+    let top_level: Vec<&archive::Member> = members
+        .iter()
+        .filter(|member| member.path.parent() == Some(root))
+        .collect();
+    stats::output_archive(context, &top_level)?;
+    write_separator(context)?;
+    for member in top_level {
+        let name = member
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let name = escapes::sanitize(&context.args, &name);
+        if member.kind == archive::Kind::Directory {
+            writeln!(context.stdout, "{}/", name.blue().bold())?;
+        } else {
+            writeln!(context.stdout, "{}", name)?;
+        }
+    }
+    write_separator(context)?;
+    tree::output_archive(context, members, root)
+}
+
+fn output_file_listing(
+    context: &mut Context,
+    statuses: Option<&git::Statuses>,
+    children: &[fs::DirEntry],
+    tree: &tree::Tree,
+) -> R<()> {
     for child in children {
+        if !filter::matches(&context.args, child)? && !tree.contains(&child.path()) {
+            continue;
+        }
         let path = format_dir_entry(child)?;
+        let path = escapes::sanitize(&context.args, &path).into_owned();
         let list_entry = if child.path().is_dir() {
             format!("{}/", path.blue().bold())
         } else {
             path
         };
-        context
-            .stdout
-            .write_all(format!("{}\n", list_entry).as_bytes())?;
+        context.stdout.write_all(
+            format!("{}{}\n", list_entry, render_status(statuses, &child.path())).as_bytes(),
+        )?;
     }
     Ok(())
 }
 
+/// Hidden entries are left out of the tree and of directory diffs.
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+fn render_status(statuses: Option<&git::Statuses>, path: &Path) -> String {
+    match statuses.and_then(|statuses| statuses.get(path)) {
+        Some(status) => format!(" {}", status.marker()),
+        None => "".to_string(),
+    }
+}
+
 fn format_dir_entry(dir_entry: &fs::DirEntry) -> R<String> {
     Ok(dir_entry
         .path()
@@ -62,6 +166,17 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn neutralizes_escape_sequences_in_names() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a\u{1b}]0;pwned\u{7}b"), "")?;
+        setup.run(vec!["."])?;
+        assert!(!setup.stdout().contains("\u{1b}]0;pwned"));
+        assert_eq!(setup.get_section(1), "a^[]0;pwned^Gb\n");
+        assert_eq!(setup.get_section(2), "a^[]0;pwned^Gb\n");
+        Ok(())
+    }
+
     #[test]
     fn multiple_files_sorted() -> R<()> {
         let mut setup = setup()?;
@@ -99,4 +214,305 @@ mod test {
         assert_eq!(setup.get_section(1), format!("{}/\n", "foo".blue().bold()));
         Ok(())
     }
+
+    mod git_status {
+        use super::*;
+
+        fn setup_repository() -> R<Setup> {
+            let setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::create_dir("dir")?;
+            fs::write(setup.tempdir().join("dir/tracked"), "foo")?;
+            fs::write(setup.tempdir().join("clean"), "foo")?;
+            fs::write(setup.tempdir().join(".gitignore"), "ignored\n")?;
+            setup.git(&["add", "."])?;
+            setup.git(&["commit", "-q", "-m", "initial"])?;
+            Ok(setup)
+        }
+
+        #[test]
+        fn does_not_show_markers_outside_of_repositories() -> R<()> {
+            let mut setup = setup()?;
+            fs::write(setup.tempdir().join("foo"), "")?;
+            setup.run(vec!["."])?;
+            assert_eq!(setup.get_section(1), "foo\n");
+            Ok(())
+        }
+
+        #[test]
+        fn clean_entries_have_no_markers() -> R<()> {
+            let mut setup = setup_repository()?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                strip(setup.get_section(2))?,
+                b".git/\n.gitignore\nclean\ndir/\n"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn shows_markers_for_each_status() -> R<()> {
+            let mut setup = setup_repository()?;
+            fs::write(setup.tempdir().join("clean"), "bar")?;
+            fs::write(setup.tempdir().join("ignored"), "")?;
+            fs::write(setup.tempdir().join("staged"), "")?;
+            fs::write(setup.tempdir().join("untracked"), "")?;
+            setup.git(&["add", "staged"])?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                strip(setup.get_section(2))?,
+                b".git/\n.gitignore\nclean M\ndir/\nignored !\nstaged S\nuntracked ?\n"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn shows_markers_for_non_utf8_names() -> R<()> {
+            use std::os::unix::ffi::OsStrExt;
+            let mut setup = setup_repository()?;
+            fs::write(
+                setup
+                    .tempdir()
+                    .join(std::ffi::OsStr::from_bytes(b"caf\xe9")),
+                "",
+            )?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                strip(setup.get_section(2))?,
+                ".git/\n.gitignore\ncaf\u{fffd} ?\nclean\ndir/\n".as_bytes()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn directories_aggregate_the_status_of_their_children() -> R<()> {
+            let mut setup = setup_repository()?;
+            fs::write(setup.tempdir().join("dir/tracked"), "bar")?;
+            fs::write(setup.tempdir().join("dir/new"), "")?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                strip(setup.get_section(3))?,
+                "clean\ndir M\n├── new ?\n└── tracked M\n".as_bytes()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn entries_in_untracked_directories_inherit_the_status() -> R<()> {
+            let mut setup = setup_repository()?;
+            fs::create_dir("new")?;
+            fs::write(setup.tempdir().join("new/file"), "")?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                strip(setup.get_section(3))?,
+                "clean\ndir\n└── tracked\nnew ?\n└── file ?\n".as_bytes()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn works_when_listing_a_subdirectory() -> R<()> {
+            let mut setup = setup_repository()?;
+            fs::write(setup.tempdir().join("dir/tracked"), "bar")?;
+            setup.run(vec!["dir"])?;
+            assert_eq!(strip(setup.get_section(2))?, b"tracked M\n");
+            Ok(())
+        }
+    }
+
+    mod repository_summary {
+        use super::*;
+
+        #[test]
+        fn is_skipped_outside_of_repositories() -> R<()> {
+            let mut setup = setup()?;
+            fs::write(setup.tempdir().join("foo"), "")?;
+            setup.run(vec!["."])?;
+            assert_eq!(setup.get_section(0), "1 entry, 0 directories, 1 file\n");
+            Ok(())
+        }
+
+        #[test]
+        fn shows_branch_head_and_dirty_files() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "")?;
+            fs::write(setup.tempdir().join(".gitignore"), "ignored\n")?;
+            setup.git(&["add", "foo", ".gitignore"])?;
+            setup.git(&["commit", "-q", "-m", "initial commit"])?;
+            fs::write(setup.tempdir().join("foo"), "changed")?;
+            fs::write(setup.tempdir().join("bar"), "")?;
+            fs::write(setup.tempdir().join("ignored"), "")?;
+            setup.run(vec!["."])?;
+            let hash = setup.git(&["rev-parse", "--short", "HEAD"])?;
+            assert_eq!(
+                setup.get_section(0),
+                format!(
+                    "branch: main\nHEAD: {} initial commit\ndirty files: 2\n",
+                    hash.trim_end()
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn shows_ahead_and_behind_counts_against_the_upstream() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            setup.git(&["commit", "-q", "--allow-empty", "-m", "first"])?;
+            setup.git(&["branch", "base"])?;
+            setup.git(&["branch", "-q", "--set-upstream-to", "base"])?;
+            setup.git(&["commit", "-q", "--allow-empty", "-m", "second"])?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                get_line(setup.get_section(0), 0),
+                "branch: main (ahead 1, behind 0 of base)"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn works_in_repositories_without_commits() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                setup.get_section(0),
+                "branch: main\nHEAD: (no commits)\ndirty files: 0\n"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn shows_the_summary_for_subdirectories() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::create_dir("dir")?;
+            setup.run(vec!["dir"])?;
+            assert_eq!(get_line(setup.get_section(0), 0), "branch: main");
+            Ok(())
+        }
+    }
+
+    mod archives {
+        use super::*;
+        use crate::archive::test::{write_tar, write_zip};
+        use crate::compression::test::compress;
+        use crate::compression::Compression;
+        use flate2::write::GzEncoder;
+
+        #[test]
+        fn lists_tar_members_like_a_directory() -> R<()> {
+            let mut setup = setup()?;
+            write_tar(
+                "foo.tar",
+                &[("dir/bar", "bar"), ("dir/baz", "baz"), ("qux", "")],
+            )?;
+            setup.run(vec!["foo.tar"])?;
+            assert_eq!(
+                setup.get_section(0),
+                "archive: ./foo.tar, 3584 bytes, 6 unpacked\n"
+            );
+            assert_eq!(setup.get_section(1), "2 entries, 1 directory, 1 file\n");
+            assert_eq!(strip(setup.get_section(2))?, b"dir/\nqux\n");
+            assert_eq!(
+                setup.get_section(3),
+                "dir (6)\n├── bar (3)\n└── baz (3)\nqux (0)\n"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn reads_gzipped_tar_files() -> R<()> {
+            let mut setup = setup()?;
+            write_tar("foo.tar", &[("foo", "foo")])?;
+            let mut encoder = GzEncoder::new(fs::File::create("foo.tgz")?, Default::default());
+            std::io::copy(&mut fs::File::open("foo.tar")?, &mut encoder)?;
+            encoder.finish()?;
+            setup.run(vec!["foo.tgz"])?;
+            assert_eq!(setup.get_section(3), "foo (3)\n");
+            Ok(())
+        }
+
+        #[test]
+        fn reads_tar_files_compressed_with_other_formats() -> R<()> {
+            let mut setup = setup()?;
+            write_tar("foo.tar", &[("foo", "foo")])?;
+            let compressed = compress(Compression::Xz, &fs::read("foo.tar")?)?;
+            fs::write("foo.tar.xz", compressed)?;
+            setup.run(vec!["foo.tar.xz"])?;
+            assert_eq!(setup.get_section(3), "foo (3)\n");
+            Ok(())
+        }
+
+        #[test]
+        fn shows_compression_ratios_of_zip_members() -> R<()> {
+            let mut setup = setup()?;
+            let contents = "a".repeat(1000);
+            write_zip("foo.zip", &[("dir/foo", &contents), ("bar", "")])?;
+            setup.run(vec!["foo.zip"])?;
+            let tree = setup.get_section(3);
+            assert_eq!(get_line(tree.clone(), 0), "bar (0)");
+            assert!(get_line(tree.clone(), 1).starts_with("dir (1000, "));
+            assert!(get_line(tree, 2).starts_with("└── foo (1000, 1%)"));
+            Ok(())
+        }
+
+        #[test]
+        fn shows_subdirectories_of_archives() -> R<()> {
+            let mut setup = setup()?;
+            write_tar(
+                "foo.tar",
+                &[("dir/sub/bar", "bar"), ("dir/baz", "baz"), ("qux", "")],
+            )?;
+            setup.run(vec!["foo.tar//dir"])?;
+            assert_eq!(
+                setup.get_section(0),
+                "archive directory: ./foo.tar//dir, 6 unpacked\n"
+            );
+            assert_eq!(setup.get_section(1), "2 entries, 1 directory, 1 file\n");
+            assert_eq!(strip(setup.get_section(2))?, b"baz\nsub/\n");
+            assert_eq!(setup.get_section(3), "baz (3)\nsub (3)\n└── bar (3)\n");
+            Ok(())
+        }
+
+        #[test]
+        fn shows_member_files_with_the_file_view() -> R<()> {
+            let mut setup = setup()?;
+            write_zip("foo.zip", &[("dir/bar.toml", "a = 1\n")])?;
+            setup.run(vec!["foo.zip//dir/bar.toml"])?;
+            assert_eq!(
+                setup.get_section(0),
+                "file: ./foo.zip//dir/bar.toml, 6 bytes\n\
+                 type: text/x-toml, utf-8, lf line endings\n"
+            );
+            assert_eq!(strip(setup.get_section(2))?, b"1 | a = 1\n");
+            Ok(())
+        }
+
+        #[test]
+        fn allows_double_slashes_before_the_archive() -> R<()> {
+            let mut setup = setup()?;
+            fs::create_dir("dir")?;
+            write_tar("dir/x.tar", &[("member", "foo\n")])?;
+            setup.run(vec!["dir//x.tar//member"])?;
+            assert!(setup
+                .get_section(0)
+                .starts_with("file: ./dir//x.tar//member, 4 bytes\n"));
+            assert_eq!(setup.get_section(1), "1 | foo\n");
+            Ok(())
+        }
+
+        #[test]
+        fn reports_missing_members() -> R<()> {
+            let mut setup = setup()?;
+            write_tar("foo.tar", &[("foo", "")])?;
+            let result = setup.run(vec!["foo.tar//bar"]);
+            assert_eq!(
+                result.map_err(|x| x.to_string()),
+                Err("path not found: ./foo.tar//bar\n".to_string())
+            );
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,265 @@
+use super::is_hidden;
+use super::tree::render_prefix;
+use crate::file::escapes;
+use crate::utils::render_path;
+use crate::{write_separator, Context, R};
+use colored::*;
+use source::Source;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+struct Entry {
+    name: OsString,
+    change: Option<Change>,
+    children: Vec<Entry>,
+}
+
+pub fn output(context: &mut Context, old: &Path, new: &Path) -> R<()> {
+    let entries = compare(old, new)?;
+    writeln!(
+        context.stdout,
+        "diff: {} → {}",
+        render_path(old),
+        render_path(new)
+    )?;
+    write_separator(context)?;
+    let mut counts = [0, 0, 0];
+    count(&entries, &mut counts);
+    writeln!(
+        context.stdout,
+        "{} added, {} removed, {} changed",
+        counts[0], counts[1], counts[2]
+    )?;
+    if !entries.is_empty() {
+        write_separator(context)?;
+        output_entries(context, entries, vec![])?;
+    }
+    Ok(())
+}
+
+/// Compares two directories recursively. Only entries that differ (and
+/// the directories containing them) are returned. Hidden entries are
+/// skipped, like in the tree.
+fn compare(old: &Path, new: &Path) -> R<Vec<Entry>> {
+    let mut names = BTreeSet::new();
+    for directory in [old, new] {
+        for entry in directory.read_dir()? {
+            let name = entry?.file_name();
+            if !is_hidden(&name) {
+                names.insert(name);
+            }
+        }
+    }
+    let mut result = vec![];
+    for name in names {
+        let old_child = old.join(&name);
+        let new_child = new.join(&name);
+        let entry = match (
+            fs::symlink_metadata(&old_child).ok(),
+            fs::symlink_metadata(&new_child).ok(),
+        ) {
+            (Some(_), None) => Some(Entry {
+                name,
+                change: Some(Change::Removed),
+                children: vec![],
+            }),
+            (None, Some(_)) => Some(Entry {
+                name,
+                change: Some(Change::Added),
+                children: vec![],
+            }),
+            (Some(old_metadata), Some(new_metadata)) => {
+                let (old_type, new_type) = (old_metadata.file_type(), new_metadata.file_type());
+                if old_type.is_dir() && new_type.is_dir() {
+                    let children = compare(&old_child, &new_child)?;
+                    if children.is_empty() {
+                        None
+                    } else {
+                        Some(Entry {
+                            name,
+                            change: None,
+                            children,
+                        })
+                    }
+                } else {
+                    let changed = if old_type.is_file() && new_type.is_file() {
+                        fs::read(&old_child)? != fs::read(&new_child)?
+                    } else if old_type.is_symlink() && new_type.is_symlink() {
+                        fs::read_link(&old_child)? != fs::read_link(&new_child)?
+                    } else {
+                        old_type != new_type
+                    };
+                    if changed {
+                        Some(Entry {
+                            name,
+                            change: Some(Change::Changed),
+                            children: vec![],
+                        })
+                    } else {
+                        None
+                    }
+                }
+            }
+            (None, None) => None,
+        };
+        result.extend(entry);
+    }
+    Ok(result)
+}
+
+fn count(entries: &[Entry], counts: &mut [usize; 3]) {
+    for entry in entries {
+        match entry.change {
+            Some(Change::Added) => counts[0] += 1,
+            Some(Change::Removed) => counts[1] += 1,
+            Some(Change::Changed) => counts[2] += 1,
+            None => count(&entry.children, counts),
+        }
+    }
+}
+
+fn output_entries(context: &mut Context, entries: Vec<Entry>, parent_prefix: Vec<bool>) -> R<()> {
+    let mut source = Source::from(entries.into_iter());
+    while let Some(entry) = source.next() {
+        let prefix = {
+            let mut clone = parent_prefix.clone();
+            clone.push(source.has_next());
+            clone
+        };
+        let name = entry.name.to_string_lossy();
+        let name = escapes::sanitize(&context.args, &name);
+        let name = match entry.change {
+            Some(Change::Added) => format!("{} {}", "+".green(), name.green()),
+            Some(Change::Removed) => format!("{} {}", "-".red(), name.red()),
+            Some(Change::Changed) => format!("{} {}", "~".yellow(), name.yellow()),
+            None => name.into_owned(),
+        };
+        writeln!(context.stdout, "{}{}", render_prefix(prefix.clone()), name)?;
+        output_entries(context, entry.children, prefix)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::{separator, R};
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn shows_added_removed_and_changed_entries() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("a/dir")?;
+        fs::create_dir_all("b/dir")?;
+        fs::write(setup.tempdir().join("a/same"), "foo")?;
+        fs::write(setup.tempdir().join("b/same"), "foo")?;
+        fs::write(setup.tempdir().join("a/removed"), "")?;
+        fs::write(setup.tempdir().join("b/added"), "")?;
+        fs::write(setup.tempdir().join("a/dir/changed"), "foo")?;
+        fs::write(setup.tempdir().join("b/dir/changed"), "bar")?;
+        fs::write(setup.tempdir().join("b/dir/new"), "")?;
+        setup.run(vec!["a", "--diff", "b"])?;
+        assert_eq!(setup.get_section(0), "diff: ./a → ./b\n");
+        assert_eq!(setup.get_section(1), "2 added, 1 removed, 1 changed\n");
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "+ added\ndir\n├── ~ changed\n└── + new\n- removed\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn skips_hidden_entries() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("a/.git")?;
+        fs::create_dir_all("b")?;
+        fs::write(setup.tempdir().join("a/.git/HEAD"), "")?;
+        fs::write(setup.tempdir().join("b/.hidden"), "")?;
+        fs::write(setup.tempdir().join("b/visible"), "")?;
+        setup.run(vec!["a", "--diff", "b"])?;
+        assert_eq!(setup.get_section(1), "1 added, 0 removed, 0 changed\n");
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "+ visible\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_names() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("a")?;
+        fs::create_dir_all("b")?;
+        fs::write(setup.tempdir().join("b/a\u{1b}]0;pwned\u{7}b"), "")?;
+        setup.run(vec!["a", "--diff", "b"])?;
+        assert!(!setup.stdout().contains("\u{1b}]0;pwned"));
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "+ a^[]0;pwned^Gb\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compares_entries_with_non_utf8_names() -> R<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let mut setup = setup()?;
+        fs::create_dir_all("a")?;
+        fs::create_dir_all("b")?;
+        for (directory, contents) in [("a", "foo"), ("b", "bar")] {
+            let directory = setup.tempdir().join(directory);
+            fs::write(directory.join(OsStr::from_bytes(b"caf\xe9")), contents)?;
+            fs::write(directory.join(OsStr::from_bytes(b"caf\xe8")), "same")?;
+        }
+        setup.run(vec!["a", "--diff", "b"])?;
+        assert_eq!(setup.get_section(1), "0 added, 0 removed, 1 changed\n");
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "~ caf\u{fffd}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn identical_directories_have_no_tree() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("a/dir")?;
+        fs::create_dir_all("b/dir")?;
+        fs::write(setup.tempdir().join("a/dir/file"), "foo")?;
+        fs::write(setup.tempdir().join("b/dir/file"), "foo")?;
+        setup.run(vec!["a", "--diff", "b"])?;
+        assert_eq!(
+            setup
+                .stdout()
+                .split(&separator(TEST_TERMINAL_WIDTH))
+                .count(),
+            2
+        );
+        assert_eq!(setup.get_section(1), "0 added, 0 removed, 0 changed\n");
+        Ok(())
+    }
+
+    #[test]
+    fn files_and_directories_cannot_be_compared() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir("a")?;
+        fs::write(setup.tempdir().join("b"), "")?;
+        let result = setup.run(vec!["a", "--diff", "b"]);
+        assert_eq!(
+            result.map_err(|x| x.to_string()),
+            Err("cannot compare directory ./a with file ./b\n".to_string())
+        );
+        Ok(())
+    }
+}
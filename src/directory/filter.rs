@@ -0,0 +1,178 @@
+use crate::args::{Args, EntryType};
+use crate::R;
+use std::fs;
+
+pub fn is_excluded(args: &Args, entry: &fs::DirEntry) -> bool {
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    args.exclude.iter().any(|pattern| pattern.matches(&name))
+}
+
+/// Whether an entry should be shown on its own merit, ignoring its
+/// descendants. Without any filters given, all entries match.
+pub fn matches(args: &Args, entry: &fs::DirEntry) -> R<bool> {
+    if is_excluded(args, entry) {
+        return Ok(false);
+    }
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    if !args.include.is_empty() && !args.include.iter().any(|pattern| pattern.matches(&name)) {
+        return Ok(false);
+    }
+    Ok(match args.entry_type {
+        None => true,
+        Some(entry_type) => {
+            let file_type = entry.file_type()?;
+            match entry_type {
+                EntryType::File => file_type.is_file(),
+                EntryType::Directory => file_type.is_dir(),
+                EntryType::Symlink => file_type.is_symlink(),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::directory::tree::test::dedent;
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    fn setup_crate() -> R<Setup> {
+        let setup = setup()?;
+        fs::create_dir_all("src/bin")?;
+        fs::create_dir_all("target/debug")?;
+        fs::create_dir_all("docs")?;
+        fs::write(setup.tempdir().join("Cargo.toml"), "")?;
+        fs::write(setup.tempdir().join("src/main.rs"), "")?;
+        fs::write(setup.tempdir().join("src/bin/tool.rs"), "")?;
+        fs::write(setup.tempdir().join("src/notes.txt"), "")?;
+        fs::write(setup.tempdir().join("target/debug/build.rs"), "")?;
+        fs::write(setup.tempdir().join("docs/index.md"), "")?;
+        Ok(setup)
+    }
+
+    #[test]
+    fn include_prunes_directories_without_matching_descendants() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![".", "--include", "*.rs"])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    src
+                    ├── bin
+                    │   └── tool.rs
+                    └── main.rs
+                    target
+                    └── debug
+                        └── build.rs
+                "
+            )
+        );
+        assert_eq!(strip(setup.get_section(1))?, b"src/\ntarget/\n");
+        Ok(())
+    }
+
+    #[test]
+    fn exclude_hides_whole_subtrees() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![".", "--include", "*.rs", "--exclude", "target"])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    src
+                    ├── bin
+                    │   └── tool.rs
+                    └── main.rs
+                "
+            )
+        );
+        assert_eq!(strip(setup.get_section(1))?, b"src/\n");
+        Ok(())
+    }
+
+    #[test]
+    fn exclude_alone_keeps_everything_else() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![".", "--exclude", "src"])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    Cargo.toml
+                    docs
+                    └── index.md
+                    target
+                    └── debug
+                        └── build.rs
+                "
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filters_by_regexes() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![
+            ".",
+            "--include-regex",
+            r"^(main|tool)\.rs$",
+            "--exclude-regex",
+            "^targ",
+        ])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    src
+                    ├── bin
+                    │   └── tool.rs
+                    └── main.rs
+                "
+            )
+        );
+        assert_eq!(strip(setup.get_section(1))?, b"src/\n");
+        Ok(())
+    }
+
+    #[test]
+    fn type_filters_by_entry_type() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![".", "--type", "d"])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    docs
+                    src
+                    └── bin
+                    target
+                    └── debug
+                "
+            )
+        );
+        assert_eq!(strip(setup.get_section(1))?, b"docs/\nsrc/\ntarget/\n");
+        Ok(())
+    }
+
+    #[test]
+    fn type_f_lists_only_files() -> R<()> {
+        let mut setup = setup_crate()?;
+        setup.run(vec![
+            ".",
+            "--type",
+            "f",
+            "--exclude",
+            "src",
+            "--exclude",
+            "target",
+        ])?;
+        assert_eq!(strip(setup.get_section(1))?, b"Cargo.toml\ndocs/\n");
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
+use crate::archive;
+use crate::utils::{pluralize, render_path};
 use crate::{Context, R};
 use std::fmt::Display;
 use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 pub fn output(context: &mut Context, children: &[fs::DirEntry]) -> R<()> {
     let stats = get_stats(children)?;
@@ -10,18 +15,35 @@ pub fn output(context: &mut Context, children: &[fs::DirEntry]) -> R<()> {
     Ok(())
 }
 
+pub fn output_archive(context: &mut Context, members: &[&archive::Member]) -> R<()> {
+    let mut stats = Stats::default();
+    for member in members {
+        stats.entries += 1;
+        match member.kind {
+            archive::Kind::Directory => stats.directories += 1,
+            archive::Kind::File => stats.files += 1,
+            archive::Kind::Symlink => stats.symlinks += 1,
+            archive::Kind::Other => {}
+        }
+    }
+    writeln!(context.stdout, "{}", stats)?;
+    Ok(())
+}
+
+#[derive(Default)]
 pub struct Stats {
     entries: usize,
     directories: usize,
     files: usize,
+    symlinks: usize,
+    broken_symlinks: usize,
+    fifos: usize,
+    sockets: usize,
+    devices: usize,
 }
 
 fn get_stats(entries: &[fs::DirEntry]) -> R<Stats> {
-    let mut stats = Stats {
-        entries: 0,
-        directories: 0,
-        files: 0,
-    };
+    let mut stats = Stats::default();
     for entry in entries {
         stats.entries += 1;
         let file_type = entry.file_type()?;
@@ -29,6 +51,18 @@ fn get_stats(entries: &[fs::DirEntry]) -> R<Stats> {
             stats.directories += 1;
         } else if file_type.is_file() {
             stats.files += 1;
+        } else if file_type.is_symlink() {
+            if entry.path().exists() {
+                stats.symlinks += 1;
+            } else {
+                stats.broken_symlinks += 1;
+            }
+        } else if file_type.is_fifo() {
+            stats.fifos += 1;
+        } else if file_type.is_socket() {
+            stats.sockets += 1;
+        } else if file_type.is_block_device() || file_type.is_char_device() {
+            stats.devices += 1;
         }
     }
     Ok(stats)
@@ -36,23 +70,128 @@ fn get_stats(entries: &[fs::DirEntry]) -> R<Stats> {
 
 impl Display for Stats {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        let entries = match self.entries {
-            1 => "entry",
-            _ => "entries",
-        };
-        let directories = match self.directories {
-            1 => "directory",
-            _ => "directories",
-        };
-        let files = match self.files {
-            1 => "file",
-            _ => "files",
-        };
-        write!(
+        let mut counts = vec![
+            pluralize(self.entries, "entry", "entries"),
+            pluralize(self.directories, "directory", "directories"),
+            pluralize(self.files, "file", "files"),
+        ];
+        let special = [
+            (self.symlinks, "symlink", "symlinks"),
+            (self.broken_symlinks, "broken symlink", "broken symlinks"),
+            (self.fifos, "fifo", "fifos"),
+            (self.sockets, "socket", "sockets"),
+            (self.devices, "device", "devices"),
+        ];
+        for (count, singular, plural) in special {
+            if count > 0 {
+                counts.push(pluralize(count, singular, plural));
+            }
+        }
+        write!(formatter, "{}", counts.join(", "))?;
+        Ok(())
+    }
+}
+
+/// Totals over all entries shown in the tree, i.e. excluding hidden files.
+/// Gets filled in by `Tree::read` while it walks the directory.
+#[derive(Default)]
+pub struct RecursiveStats {
+    directories: usize,
+    files: usize,
+    symlinks: usize,
+    special: usize,
+    apparent_size: u64,
+    disk_usage: u64,
+    max_depth: usize,
+    largest_file: Option<(PathBuf, u64)>,
+    newest_file: Option<(PathBuf, SystemTime)>,
+    oldest_file: Option<(PathBuf, SystemTime)>,
+}
+
+impl RecursiveStats {
+    pub fn add(&mut self, path: PathBuf, metadata: &fs::Metadata, depth: usize) -> R<()> {
+        self.apparent_size += metadata.len();
+        self.disk_usage += metadata.blocks() * 512;
+        self.max_depth = self.max_depth.max(depth);
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            self.directories += 1;
+        } else if file_type.is_file() {
+            self.files += 1;
+            if self
+                .largest_file
+                .as_ref()
+                .is_none_or(|(_, size)| metadata.len() > *size)
+            {
+                self.largest_file = Some((path.clone(), metadata.len()));
+            }
+            let modified = metadata.modified()?;
+            if self
+                .newest_file
+                .as_ref()
+                .is_none_or(|(_, time)| modified > *time)
+            {
+                self.newest_file = Some((path.clone(), modified));
+            }
+            if self
+                .oldest_file
+                .as_ref()
+                .is_none_or(|(_, time)| modified < *time)
+            {
+                self.oldest_file = Some((path, modified));
+            }
+        } else if file_type.is_symlink() {
+            self.symlinks += 1;
+        } else {
+            self.special += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RecursiveStats {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let mut counts = vec![
+            pluralize(self.directories, "directory", "directories"),
+            pluralize(self.files, "file", "files"),
+        ];
+        if self.symlinks > 0 {
+            counts.push(pluralize(self.symlinks, "symlink", "symlinks"));
+        }
+        if self.special > 0 {
+            counts.push(pluralize(self.special, "special entry", "special entries"));
+        }
+        writeln!(formatter, "recursive: {}", counts.join(", "))?;
+        writeln!(
             formatter,
-            "{} {}, {} {}, {} {}",
-            self.entries, entries, self.directories, directories, self.files, files
+            "apparent size: {} bytes, disk usage: {} bytes",
+            self.apparent_size, self.disk_usage
         )?;
+        write!(formatter, "deepest nesting level: {}", self.max_depth)?;
+        if let Some((path, size)) = &self.largest_file {
+            write!(
+                formatter,
+                "\nlargest file: {}, {} bytes",
+                render_path(path),
+                size
+            )?;
+        }
+        if let Some((path, time)) = &self.newest_file {
+            write!(
+                formatter,
+                "\nnewest file: {}, {}",
+                render_path(path),
+                humantime::format_rfc3339_seconds(*time)
+            )?;
+        }
+        if let Some((path, time)) = &self.oldest_file {
+            write!(
+                formatter,
+                "\noldest file: {}, {}",
+                render_path(path),
+                humantime::format_rfc3339_seconds(*time)
+            )?;
+        }
         Ok(())
     }
 }
@@ -83,7 +222,8 @@ mod stats_pluralization {
                 Stats {
                     entries: 0,
                     directories: 0,
-                    files: 0
+                    files: 0,
+                    ..Stats::default()
                 }
             ),
             "0 entries, 0 directories, 0 files"
@@ -98,7 +238,8 @@ mod stats_pluralization {
                 Stats {
                     entries: 1,
                     directories: 1,
-                    files: 1
+                    files: 1,
+                    ..Stats::default()
                 }
             ),
             "1 entry, 1 directory, 1 file"
@@ -113,10 +254,135 @@ mod stats_pluralization {
                 Stats {
                     entries: 2,
                     directories: 2,
-                    files: 2
+                    files: 2,
+                    ..Stats::default()
                 }
             ),
             "2 entries, 2 directories, 2 files"
         );
     }
+
+    #[test]
+    fn shows_special_entries_when_present() {
+        assert_eq!(
+            format!(
+                "{}",
+                Stats {
+                    entries: 4,
+                    symlinks: 2,
+                    broken_symlinks: 1,
+                    sockets: 1,
+                    ..Stats::default()
+                }
+            ),
+            "4 entries, 0 directories, 0 files, 2 symlinks, 1 broken symlink, 1 socket"
+        );
+    }
+
+    #[test]
+    fn counts_symlinks_fifos_and_sockets() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo"), "")?;
+        std::os::unix::fs::symlink("foo", "live")?;
+        std::os::unix::fs::symlink("does_not_exist", "broken")?;
+        std::process::Command::new("mkfifo").arg("fifo").status()?;
+        let _socket = std::os::unix::net::UnixListener::bind("socket")?;
+        setup.run(vec!["."])?;
+        assert_eq!(
+            setup.get_section(0),
+            "5 entries, 0 directories, 1 file, 1 symlink, 1 broken symlink, 1 fifo, 1 socket\n"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod recursive_stats {
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn is_not_shown_by_default() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo"), "")?;
+        setup.run(vec!["."])?;
+        assert_eq!(setup.stdout().matches("recursive:").count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn counts_entries_in_all_subdirectories() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("foo/bar")?;
+        fs::write(setup.tempdir().join("foo/bar/baz"), "")?;
+        fs::write(setup.tempdir().join("foo/qux"), "")?;
+        std::os::unix::fs::symlink("foo/qux", "link")?;
+        setup.run(vec!["--recursive-stats", "."])?;
+        assert_eq!(
+            get_line(setup.get_section(3), 0),
+            "recursive: 2 directories, 2 files, 1 symlink"
+        );
+        assert_eq!(
+            get_line(setup.get_section(3), 2),
+            "deepest nesting level: 3"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_follow_symlinks_to_directories() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir("dir")?;
+        fs::write(setup.tempdir().join("dir/foo"), "")?;
+        std::os::unix::fs::symlink("dir", "link")?;
+        std::os::unix::fs::symlink(".", "loop")?;
+        setup.run(vec!["--recursive-stats", "."])?;
+        assert_eq!(
+            get_line(setup.get_section(3), 0),
+            "recursive: 1 directory, 1 file, 2 symlinks"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_the_largest_file_and_the_total_size() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir("dir")?;
+        fs::write(setup.tempdir().join("dir/big"), "foobar")?;
+        fs::write(setup.tempdir().join("small"), "foo")?;
+        setup.run(vec!["--recursive-stats", "."])?;
+        assert!(get_line(setup.get_section(3), 1).starts_with("apparent size: "));
+        assert_eq!(
+            get_line(setup.get_section(3), 3),
+            "largest file: ./dir/big, 6 bytes"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_the_newest_and_oldest_file() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("new"), "")?;
+        fs::write(setup.tempdir().join("old"), "")?;
+        fs::File::options()
+            .write(true)
+            .open("old")?
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(86400))?;
+        fs::File::options()
+            .write(true)
+            .open("new")?
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(2 * 86400))?;
+        setup.run(vec!["--recursive-stats", "."])?;
+        assert_eq!(
+            get_line(setup.get_section(3), 4),
+            "newest file: ./new, 1970-01-03T00:00:00Z"
+        );
+        assert_eq!(
+            get_line(setup.get_section(3), 5),
+            "oldest file: ./old, 1970-01-02T00:00:00Z"
+        );
+        Ok(())
+    }
 }
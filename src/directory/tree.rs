@@ -1,41 +1,262 @@
-use crate::directory::{format_dir_entry, read_directory};
+use crate::archive;
+use crate::directory::stats::RecursiveStats;
+use crate::directory::{filter, format_dir_entry, is_hidden, read_directory, render_status};
+use crate::file::escapes;
+use crate::git;
+use crate::utils::render_size;
 use crate::{Context, R};
 use source::Source;
+use std::collections::BTreeMap;
 use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 
-pub fn output(context: &mut Context, children: Vec<fs::DirEntry>) -> R<()> {
-    output_children(context, children, vec![])
+const BAR_WIDTH: usize = 10;
+
+struct Node {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    /// `None` for entries on disk, `Some` for archive members. The inner
+    /// value is the compressed size, if the archive format has one.
+    archived: Option<Option<u64>>,
+    children: Vec<Node>,
+}
+
+/// The filtered tree below a directory. Reading it walks the directory
+/// once, collecting the recursive stats on the way.
+pub struct Tree {
+    nodes: Vec<Node>,
+    stats: RecursiveStats,
+}
+
+impl Tree {
+    pub fn read(context: &Context, children: &[fs::DirEntry]) -> R<Tree> {
+        let mut stats = RecursiveStats::default();
+        let nodes = read_nodes(context, &mut stats, children, 1)?;
+        Ok(Tree { nodes, stats })
+    }
+
+    /// Whether a top-level entry shows up in the tree, i.e. whether it
+    /// matches the filters or contains a matching descendant.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.nodes.iter().any(|node| node.path == path)
+    }
+}
+
+pub fn output(
+    context: &mut Context,
+    statuses: Option<&git::Statuses>,
+    tree: Tree,
+) -> R<RecursiveStats> {
+    output_nodes(context, statuses, tree.nodes)?;
+    Ok(tree.stats)
+}
+
+/// Renders the members of an archive below `root`, which is a path inside
+/// the archive (empty for the whole archive).
+pub fn output_archive(context: &mut Context, members: &[archive::Member], root: &Path) -> R<()> {
+    output_nodes(context, None, archive_nodes(members, root))
+}
+
+fn output_nodes(
+    context: &mut Context,
+    statuses: Option<&git::Statuses>,
+    mut nodes: Vec<Node>,
+) -> R<()> {
+    if context.args.sort_by_size {
+        sort_by_size(&mut nodes);
+    }
+    let total = nodes.iter().map(|node| node.size).sum();
+    output_children(context, statuses, nodes, total, vec![])
+}
+
+fn read_nodes(
+    context: &Context,
+    stats: &mut RecursiveStats,
+    children: &[fs::DirEntry],
+    depth: usize,
+) -> R<Vec<Node>> {
+    let mut result = vec![];
+    for child in children {
+        if is_hidden(&child.file_name()) || filter::is_excluded(&context.args, child) {
+            continue;
+        }
+        let metadata = child.metadata()?;
+        let matches = filter::matches(&context.args, child)?;
+        // Symlinks aren't followed, so linked directories aren't counted
+        // twice and links to ancestors don't recurse forever.
+        let node = if child.file_type()?.is_dir() {
+            let grand_children = read_directory(child.path())?;
+            let children = read_nodes(context, stats, &grand_children, depth + 1)?;
+            if !matches && children.is_empty() {
+                continue;
+            }
+            Node {
+                name: format_dir_entry(child)?,
+                path: child.path(),
+                is_dir: true,
+                size: disk_usage(&metadata) + children.iter().map(|child| child.size).sum::<u64>(),
+                archived: None,
+                children,
+            }
+        } else {
+            if !matches {
+                continue;
+            }
+            Node {
+                name: format_dir_entry(child)?,
+                path: child.path(),
+                is_dir: false,
+                size: disk_usage(&metadata),
+                archived: None,
+                children: vec![],
+            }
+        };
+        stats.add(node.path.clone(), &metadata, depth)?;
+        result.push(node);
+    }
+    Ok(result)
+}
+
+/// The space allocated on disk, like `du` reports it, rather than the
+/// apparent size.
+fn disk_usage(metadata: &fs::Metadata) -> u64 {
+    metadata.blocks() * 512
+}
+
+fn archive_nodes(members: &[archive::Member], root: &Path) -> Vec<Node> {
+    let mut children: BTreeMap<&Path, Vec<&archive::Member>> = BTreeMap::new();
+    for member in members {
+        if let Some(parent) = member.path.parent() {
+            children.entry(parent).or_default().push(member);
+        }
+    }
+    archive_children(&children, root)
+}
+
+fn archive_children(children: &BTreeMap<&Path, Vec<&archive::Member>>, parent: &Path) -> Vec<Node> {
+    children
+        .get(parent)
+        .into_iter()
+        .flatten()
+        .map(|member| {
+            let is_dir = member.kind == archive::Kind::Directory;
+            let children = if is_dir {
+                archive_children(children, &member.path)
+            } else {
+                vec![]
+            };
+            let (size, compressed_size) = if is_dir {
+                (
+                    children.iter().map(|child| child.size).sum(),
+                    children.iter().map(|child| child.archived.flatten()).sum(),
+                )
+            } else {
+                (member.size, member.compressed_size)
+            };
+            Node {
+                name: member
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                path: member.path.clone(),
+                is_dir,
+                size,
+                archived: Some(compressed_size),
+                children,
+            }
+        })
+        .collect()
+}
+
+fn sort_by_size(nodes: &mut [Node]) {
+    nodes.sort_by_key(|node| std::cmp::Reverse(node.size));
+    for node in nodes {
+        sort_by_size(&mut node.children);
+    }
 }
 
 fn output_children(
     context: &mut Context,
-    children: Vec<fs::DirEntry>,
+    statuses: Option<&git::Statuses>,
+    children: Vec<Node>,
+    parent_size: u64,
     parent_prefix: Vec<bool>,
 ) -> R<()> {
     let mut source = Source::from(children.into_iter());
     while let Some(child) = source.next() {
-        if !child.file_name().to_string_lossy().starts_with('.') {
-            let child_prefix = {
-                let mut clone = parent_prefix.clone();
-                clone.push(source.has_next());
-                clone
-            };
-            writeln!(
-                context.stdout,
-                "{}{}",
-                render_prefix(child_prefix.clone()),
-                format_dir_entry(&child)?
-            )?;
-            if child.path().is_dir() {
-                let grand_children = read_directory(child.path())?;
-                output_children(context, grand_children, child_prefix)?;
-            }
-        }
+        let child_prefix = {
+            let mut clone = parent_prefix.clone();
+            clone.push(source.has_next());
+            clone
+        };
+        let size_annotation = render_size_annotation(context, &child, parent_size);
+        let status = render_status(statuses, &child.path);
+        let (name, child) = if context.args.collapse {
+            collapse(child)
+        } else {
+            (child.name.clone(), child)
+        };
+        writeln!(
+            context.stdout,
+            "{}{}{}{}",
+            render_prefix(child_prefix.clone()),
+            escapes::sanitize(&context.args, &name),
+            size_annotation,
+            status
+        )?;
+        output_children(context, statuses, child.children, child.size, child_prefix)?;
     }
     Ok(())
 }
 
-fn render_prefix(prefix: Vec<bool>) -> String {
+/// Folds chains of directories that only contain a single directory into
+/// one node, e.g. `src/main/java`. Returns the joined name and the last
+/// directory of the chain.
+fn collapse(mut node: Node) -> (String, Node) {
+    let mut name = node.name.clone();
+    while node.children.len() == 1 && node.children[0].is_dir {
+        node = node.children.remove(0);
+        name.push('/');
+        name.push_str(&node.name);
+    }
+    (name, node)
+}
+
+/// Sizes are always shown for archive members, together with the
+/// compression ratio when it is known.
+fn render_size_annotation(context: &Context, node: &Node, parent_size: u64) -> String {
+    let mut result = String::new();
+    match node.archived {
+        Some(Some(compressed_size)) if node.size > 0 => result.push_str(&format!(
+            " ({}, {}%)",
+            render_size(node.size),
+            compressed_size * 100 / node.size
+        )),
+        Some(_) => result.push_str(&format!(" ({})", render_size(node.size))),
+        None if context.args.sizes || context.args.size_bars => {
+            result.push_str(&format!(" ({})", render_size(node.size)))
+        }
+        None => {}
+    }
+    if context.args.size_bars {
+        let filled = if parent_size == 0 {
+            0
+        } else {
+            (node.size as f64 / parent_size as f64 * BAR_WIDTH as f64).round() as usize
+        };
+        result.push(' ');
+        result.push_str(&"█".repeat(filled));
+        result.push_str(&"░".repeat(BAR_WIDTH - filled));
+    }
+    result
+}
+
+pub fn render_prefix(prefix: Vec<bool>) -> String {
     let mut result = "".to_string();
     let mut source = Source::from(prefix.into_iter().skip(1));
     while let Some(level_has_next) = source.next() {
@@ -52,11 +273,11 @@ fn render_prefix(prefix: Vec<bool>) -> String {
 }
 
 #[cfg(test)]
-mod test {
+pub mod test {
     use super::*;
     use crate::test::*;
 
-    fn dedent(string: &str) -> String {
+    pub fn dedent(string: &str) -> String {
         textwrap::dedent(string)
             .chars()
             .skip_while(|x| x == &'\n')
@@ -208,4 +429,193 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn does_not_show_sizes_by_default() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo"), "foo")?;
+        setup.run(vec!["."])?;
+        assert_eq!(setup.get_section(2), "foo\n");
+        Ok(())
+    }
+
+    /// The disk usage of a single file or directory, without its children.
+    fn usage(path: &str) -> u64 {
+        disk_usage(&fs::symlink_metadata(path).unwrap())
+    }
+
+    #[test]
+    fn shows_disk_usage_with_recursive_totals_for_directories() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir_all("foo/bar")?;
+        fs::write(setup.tempdir().join("foo/bar/baz"), "a".repeat(20000))?;
+        fs::write(setup.tempdir().join("foo/qux"), "a".repeat(10000))?;
+        fs::write(setup.tempdir().join("small"), "foo")?;
+        setup.run(vec!["--sizes", "."])?;
+        let bar = usage("foo/bar") + usage("foo/bar/baz");
+        let foo = usage("foo") + bar + usage("foo/qux");
+        assert_eq!(
+            setup.get_section(2),
+            format!(
+                "foo ({})\n├── bar ({})\n│   └── baz ({})\n└── qux ({})\nsmall ({})\n",
+                render_size(foo),
+                render_size(bar),
+                render_size(usage("foo/bar/baz")),
+                render_size(usage("foo/qux")),
+                render_size(usage("small"))
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_allocated_blocks_instead_of_apparent_sizes() -> R<()> {
+        let mut setup = setup()?;
+        let file = fs::File::create("sparse")?;
+        file.set_len(100 * 1024 * 1024)?;
+        setup.run(vec!["--sizes", "."])?;
+        assert_eq!(
+            setup.get_section(2),
+            format!("sparse ({})\n", render_size(usage("sparse")))
+        );
+        assert!(usage("sparse") < 100 * 1024 * 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_by_size() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir("dir")?;
+        fs::write(setup.tempdir().join("dir/a"), "a")?;
+        fs::write(setup.tempdir().join("dir/b"), "a".repeat(30000))?;
+        fs::write(setup.tempdir().join("dir/c"), "a".repeat(15000))?;
+        fs::write(setup.tempdir().join("big"), "a".repeat(200000))?;
+        setup.run(vec!["--sort-by-size", "."])?;
+        assert_eq!(
+            setup.get_section(2),
+            dedent(
+                "
+                    big
+                    dir
+                    ├── b
+                    ├── c
+                    └── a
+                "
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn renders_bars_proportional_to_the_parent_size() -> R<()> {
+        let mut setup = setup()?;
+        fs::create_dir("dir")?;
+        fs::write(setup.tempdir().join("dir/a"), "a".repeat(30000))?;
+        fs::write(setup.tempdir().join("dir/b"), "a".repeat(10000))?;
+        setup.run(vec!["--size-bars", "."])?;
+        let dir = usage("dir") + usage("dir/a") + usage("dir/b");
+        let bar = |size: u64| {
+            let filled = (size as f64 / dir as f64 * BAR_WIDTH as f64).round() as usize;
+            format!(
+                "({}) {}{}",
+                render_size(size),
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled)
+            )
+        };
+        assert_eq!(
+            setup.get_section(2),
+            format!(
+                "dir {}\n├── a {}\n└── b {}\n",
+                bar(dir),
+                bar(usage("dir/a")),
+                bar(usage("dir/b"))
+            )
+        );
+        Ok(())
+    }
+
+    mod collapse {
+        use super::*;
+
+        #[test]
+        fn does_not_collapse_by_default() -> R<()> {
+            let mut setup = setup()?;
+            fs::create_dir_all("a/b")?;
+            fs::write(setup.tempdir().join("a/b/c"), "")?;
+            setup.run(vec!["."])?;
+            assert_eq!(
+                setup.get_section(2),
+                dedent(
+                    "
+                        a
+                        └── b
+                            └── c
+                    "
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn folds_single_child_directory_chains() -> R<()> {
+            let mut setup = setup()?;
+            fs::create_dir_all("src/main/java/com/acme")?;
+            fs::write(setup.tempdir().join("src/main/java/com/acme/App.java"), "")?;
+            fs::write(setup.tempdir().join("src/main/java/com/acme/Util.java"), "")?;
+            setup.run(vec![".", "--collapse"])?;
+            assert_eq!(
+                setup.get_section(2),
+                dedent(
+                    "
+                        src/main/java/com/acme
+                        ├── App.java
+                        └── Util.java
+                    "
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn does_not_fold_directories_containing_a_single_file() -> R<()> {
+            let mut setup = setup()?;
+            fs::create_dir_all("a/b")?;
+            fs::write(setup.tempdir().join("a/b/c"), "")?;
+            fs::write(setup.tempdir().join("d"), "")?;
+            setup.run(vec![".", "--collapse"])?;
+            assert_eq!(
+                setup.get_section(2),
+                dedent(
+                    "
+                        a/b
+                        └── c
+                        d
+                    "
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn folds_chains_in_nested_directories() -> R<()> {
+            let mut setup = setup()?;
+            fs::create_dir_all("a/b/c")?;
+            fs::create_dir_all("a/d")?;
+            fs::write(setup.tempdir().join("a/b/c/file"), "")?;
+            setup.run(vec![".", "--collapse"])?;
+            assert_eq!(
+                setup.get_section(2),
+                dedent(
+                    "
+                        a
+                        ├── b/c
+                        │   └── file
+                        └── d
+                    "
+                )
+            );
+            Ok(())
+        }
+    }
 }
@@ -1,32 +1,281 @@
 mod colorize;
+mod config;
+pub mod diff;
+mod encoding;
+pub mod escapes;
+mod executable;
+mod images;
+mod json;
 mod line_numbers;
+mod markdown;
+mod table;
+mod whitespace;
 
-use self::colorize::colorize;
+use self::colorize::{colorize_language, Language};
+use self::encoding::Encoding;
+use self::line_numbers::Location;
+use crate::compression::{self, Compression, Limited};
+use crate::git;
 use crate::utils::render_path;
 use crate::{write_separator, Context, R};
 use source::Source;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn output(context: &mut Context, file: PathBuf) -> R<()> {
-    let size = fs::metadata(&file)?.len();
-    writeln!(
+    let bytes = fs::read(&file)?;
+    let contents = Limited {
+        bytes,
+        truncated: false,
+    };
+    output_bytes(context, file, true, contents)
+}
+
+/// Shows a file that only exists inside an archive. `path` is only used for
+/// the header and to pick how the contents are shown.
+pub fn output_archive_member(context: &mut Context, path: PathBuf, contents: Limited) -> R<()> {
+    output_bytes(context, path, false, contents)
+}
+
+/// `on_disk` enables the git integrations, which need a real file.
+/// Compressed files are decompressed and then shown according to their
+/// inner extension, e.g. `app.log.gz` as `app.log`.
+fn output_bytes(context: &mut Context, file: PathBuf, on_disk: bool, contents: Limited) -> R<()> {
+    let Limited {
+        bytes,
+        mut truncated,
+    } = contents;
+    write!(
         context.stdout,
         "file: {}, {} bytes",
         render_path(&file),
-        size
+        bytes.len()
+    )?;
+    let compression = Compression::from_path(&file);
+    let bytes = match compression {
+        Some(compression) => match compression.decompress(&bytes) {
+            Ok(decompressed) => {
+                writeln!(
+                    context.stdout,
+                    ", {} compressed, {} bytes uncompressed",
+                    compression.name(),
+                    decompressed.bytes.len()
+                )?;
+                truncated |= decompressed.truncated;
+                decompressed.bytes
+            }
+            Err(error) => {
+                writeln!(context.stdout)?;
+                return Err(format!(
+                    "cannot decompress {} as {}: {}\n",
+                    render_path(&file),
+                    compression.name(),
+                    error
+                )
+                .into());
+            }
+        },
+        None => {
+            writeln!(context.stdout)?;
+            bytes
+        }
+    };
+    if truncated {
+        writeln!(
+            context.stdout,
+            "truncated after {} bytes",
+            compression::LIMIT
+        )?;
+    }
+    // The header already names the compression, so the type describes the
+    // decompressed contents.
+    let inner_file = match compression {
+        Some(_) => compression::inner_path(&file),
+        None => file.clone(),
+    };
+    let encoding = detect_encoding(context, &bytes);
+    let mime_type = mime_type(&inner_file, &bytes, encoding);
+    // Images and executables are summarized instead of shown, so they
+    // don't need to be decoded.
+    let image = images::Format::detect(&inner_file, &bytes);
+    let is_elf = executable::is_elf(&bytes);
+    let contents = match image {
+        Some(images::Format::Svg) if context.args.raw => Some(encoding.decode(&bytes)),
+        Some(_) => None,
+        None if is_elf => None,
+        None => Some(encoding.decode(&bytes)),
+    };
+    writeln!(
+        context.stdout,
+        "type: {}",
+        render_content_type(&mime_type, encoding, contents.as_deref())
     )?;
     write_separator(context)?;
-    for chunk in line_numbers::add(
-        &file,
-        colorize(Source::read_utf8_file(&file)?)
-            .flat_map(|x| Source::from(x.chars().collect::<Vec<_>>().into_iter())),
-    )? {
+    if context.args.history && on_disk {
+        if let Some(history) = git::History::read(&file) {
+            writeln!(context.stdout, "{}", history)?;
+            write_separator(context)?;
+        }
+    }
+    let on_disk = on_disk && compression.is_none();
+    let file = inner_file;
+    if let Some(format) = image {
+        images::output(context, format, &bytes)?;
+        if let (images::Format::Raster(format), true) = (format, context.args.preview) {
+            write_separator(context)?;
+            images::output_preview(context, format, &bytes)?;
+        }
+        if contents.is_none() {
+            return Ok(());
+        }
+        write_separator(context)?;
+    } else if is_elf {
+        return executable::output_elf(context, &bytes);
+    }
+    let contents = contents.unwrap_or_default();
+    let mut language = Language::from_path(&file);
+    if let Some(interpreter) = executable::interpreter(&contents) {
+        writeln!(
+            context.stdout,
+            "interpreter: {}",
+            escapes::sanitize(&context.args, &interpreter)
+        )?;
+        write_separator(context)?;
+        if language == Language::Plain {
+            language = Language::from_interpreter(&interpreter);
+        }
+    }
+    let mut error = None;
+    if has_extension(&file, &["json"]) {
+        let document = json::output(context, &contents)?;
+        write_separator(context)?;
+        match document {
+            json::Document::Valid {
+                pretty: Some(pretty),
+            } => return output_contents(context, None, language, pretty, None),
+            json::Document::Valid { pretty: None } => {}
+            json::Document::Invalid(location) => error = Some(location),
+        }
+    } else if language == Language::Toml {
+        error = config::output_toml(context, &file, &contents)?;
+        write_separator(context)?;
+    } else if language == Language::Yaml {
+        error = config::output_yaml(context, &contents)?;
+        write_separator(context)?;
+    } else if context.args.render && has_extension(&file, &["md", "markdown"]) {
+        write!(
+            context.stdout,
+            "{}",
+            markdown::render(&context.args, &contents)
+        )?;
+        return Ok(());
+    } else if let Some(delimiter) = table_delimiter(&file) {
+        let rows = table::read(&contents, delimiter)?;
+        table::output_stats(context, &rows)?;
+        write_separator(context)?;
+        if !context.args.raw {
+            return table::output_table(context, &rows);
+        }
+    }
+    let file = if on_disk { Some(file.as_path()) } else { None };
+    output_contents(context, file, language, contents, error)
+}
+
+/// Writes the numbered and colorized contents. `file` should only be given
+/// when `contents` are the unmodified contents of that file. `error` is the
+/// location of a parse error to point at.
+fn output_contents(
+    context: &mut Context,
+    file: Option<&Path>,
+    language: Language,
+    contents: String,
+    error: Option<Location>,
+) -> R<()> {
+    let contents = if context.args.show_whitespace {
+        whitespace::visualize(&contents)
+    } else {
+        contents
+    };
+    let contents = escapes::sanitize(&context.args, &contents).into_owned();
+    // Colors would break up escape sequences that are passed through.
+    let colorized = if context.args.ansi {
+        Source::from(contents.chars().collect::<Vec<_>>().into_iter())
+    } else {
+        colorize_language(&contents, language)
+            .flat_map(|x| Source::from(x.chars().collect::<Vec<_>>().into_iter()))
+    };
+    for chunk in line_numbers::add(&contents, file, context.args.blame, error, colorized) {
         write!(context.stdout, "{}", chunk)?;
     }
     Ok(())
 }
 
+/// Honors `--encoding` and falls back to detecting the encoding.
+fn detect_encoding(context: &Context, bytes: &[u8]) -> Encoding {
+    match context.args.encoding {
+        Some(encoding) => Encoding::Text {
+            encoding,
+            bom: false,
+        },
+        None => Encoding::detect(bytes),
+    }
+}
+
+/// Whether the contents are text, i.e. not binary and not of a non-text
+/// type according to their magic bytes.
+fn is_text(bytes: &[u8], encoding: Encoding) -> bool {
+    encoding != Encoding::Binary
+        && infer::get(bytes).is_none_or(|kind| kind.matcher_type() == infer::MatcherType::Text)
+}
+
+/// Detects the type by magic bytes first and falls back to the extension.
+fn mime_type(file: &Path, bytes: &[u8], encoding: Encoding) -> String {
+    if let Some(kind) = infer::get(bytes) {
+        return kind.mime_type().to_string();
+    }
+    if let Some(mime_type) = mime_guess::from_path(file).first_raw() {
+        return mime_type.to_string();
+    }
+    match encoding {
+        Encoding::Binary => "application/octet-stream".to_string(),
+        _ => "text/plain".to_string(),
+    }
+}
+
+/// Adds the encoding, the line endings and a missing trailing newline for
+/// text files. `text` is only given when the contents get shown.
+fn render_content_type(mime_type: &str, encoding: Encoding, text: Option<&str>) -> String {
+    let mut parts = vec![mime_type.to_string(), encoding.name()];
+    if let (Encoding::Text { .. }, Some(text)) = (encoding, text) {
+        if let Some(line_endings) = encoding::line_endings(text) {
+            parts.push(format!("{} line endings", line_endings));
+        }
+        if !text.is_empty() && !text.ends_with('\n') {
+            parts.push("no trailing newline".to_string());
+        }
+    }
+    parts.join(", ")
+}
+
+fn table_delimiter(file: &Path) -> Option<u8> {
+    if has_extension(file, &["csv"]) {
+        Some(b',')
+    } else if has_extension(file, &["tsv"]) {
+        Some(b'\t')
+    } else {
+        None
+    }
+}
+
+fn has_extension(file: &Path, extensions: &[&str]) -> bool {
+    match file.extension() {
+        Some(extension) => extensions
+            .iter()
+            .any(|x| extension.to_string_lossy().eq_ignore_ascii_case(x)),
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -52,6 +301,10 @@ mod test {
         assert_eq!(get_line(setup.stdout(), 0), "file: ./foo, 3 bytes");
         assert_eq!(
             get_line(setup.stdout(), 1),
+            "type: text/plain, utf-8, no trailing newline"
+        );
+        assert_eq!(
+            get_line(setup.stdout(), 2),
             Source::replicate(TEST_TERMINAL_WIDTH.unwrap() as u32, "─")
                 .join("")
                 .yellow()
@@ -61,6 +314,30 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn shows_mime_type_encoding_and_line_endings() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo.csv", b"a,\xf6\r\nb,c\r\n")?;
+        setup.run(vec!["foo.csv"])?;
+        assert_eq!(
+            get_line(setup.stdout(), 1),
+            "type: text/csv, latin-1, crlf line endings"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detects_binary_files_by_their_contents() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", b"\x00\x01\x02")?;
+        setup.run(vec!["foo"])?;
+        assert_eq!(
+            get_line(setup.stdout(), 1),
+            "type: application/octet-stream, binary"
+        );
+        Ok(())
+    }
+
     #[test]
     fn does_not_crash_for_invalid_utf_8() -> R<()> {
         let mut setup = setup()?;
@@ -68,4 +345,150 @@ mod test {
         setup.run(vec!["foo"])?;
         Ok(())
     }
+
+    mod history {
+        use super::*;
+
+        #[test]
+        fn is_not_shown_by_default() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "foo")?;
+            setup.git(&["add", "foo"])?;
+            setup.git(&["commit", "-q", "-m", "add foo"])?;
+            setup.run(vec!["foo"])?;
+            assert_eq!(setup.get_section(1), "1 | foo");
+            Ok(())
+        }
+
+        #[test]
+        fn lists_recent_commits_and_the_first_commit() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            for n in 1..=7 {
+                fs::write(setup.tempdir().join("foo"), n.to_string())?;
+                setup.git(&["add", "foo"])?;
+                setup.git(&["commit", "-q", "-m", &format!("commit {}", n)])?;
+            }
+            setup.git(&["commit", "-q", "--allow-empty", "-m", "unrelated"])?;
+            setup.run(vec!["--history", "foo"])?;
+            let hashes = setup.git(&["log", "--format=%h", "--", "foo"])?;
+            let hashes: Vec<&str> = hashes.lines().collect();
+            let line = |n: usize| format!("{} 2022-10-19 Alice: commit {}", hashes[7 - n], n);
+            assert_eq!(
+                setup.get_section(1),
+                format!(
+                    "recent commits:\n{}\n{}\n{}\n{}\n{}\nfirst commit:\n{}\n",
+                    line(7),
+                    line(6),
+                    line(5),
+                    line(4),
+                    line(3),
+                    line(1)
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn reports_untracked_files() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "foo")?;
+            setup.run(vec!["--history", "foo"])?;
+            assert_eq!(setup.get_section(1), "no commits touching this file\n");
+            Ok(())
+        }
+
+        #[test]
+        fn is_skipped_outside_of_repositories() -> R<()> {
+            let mut setup = setup()?;
+            fs::write(setup.tempdir().join("foo"), "foo")?;
+            setup.run(vec!["--history", "foo"])?;
+            assert_eq!(setup.get_section(1), "1 | foo");
+            Ok(())
+        }
+    }
+
+    mod compressed {
+        use super::*;
+        use crate::compression::test::compress;
+
+        #[test]
+        fn decompresses_files_and_shows_both_sizes() -> R<()> {
+            let mut setup = setup()?;
+            let compressed = compress(Compression::Gzip, b"foo\nbar\n")?;
+            fs::write("app.log.gz", &compressed)?;
+            setup.run(vec!["app.log.gz"])?;
+            assert_eq!(
+                setup.get_section(0),
+                format!(
+                    "file: ./app.log.gz, {} bytes, gzip compressed, 8 bytes uncompressed\n\
+                     type: text/plain, utf-8, lf line endings\n",
+                    compressed.len()
+                )
+            );
+            assert_eq!(setup.get_section(1), "1 | foo\n2 | bar\n");
+            Ok(())
+        }
+
+        #[test]
+        fn truncates_large_files() -> R<()> {
+            let mut setup = setup()?;
+            let contents = "a\n".repeat(compression::LIMIT as usize);
+            let compressed = compress(Compression::Gzip, contents.as_bytes())?;
+            fs::write("app.log.gz", &compressed)?;
+            setup.run(vec!["app.log.gz"])?;
+            assert_eq!(
+                get_line(setup.get_section(0), 0),
+                format!(
+                    "file: ./app.log.gz, {} bytes, gzip compressed, {} bytes uncompressed",
+                    compressed.len(),
+                    compression::LIMIT
+                )
+            );
+            assert_eq!(
+                get_line(setup.get_section(0), 1),
+                format!("truncated after {} bytes", compression::LIMIT)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn truncates_large_archive_members() -> R<()> {
+            let mut setup = setup()?;
+            let contents = "a\n".repeat(compression::LIMIT as usize);
+            crate::archive::test::write_tar("foo.tar", &[("big", &contents)])?;
+            setup.run(vec!["foo.tar//big"])?;
+            assert_eq!(
+                get_line(setup.get_section(0), 1),
+                format!("truncated after {} bytes", compression::LIMIT)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn uses_the_inner_extension_for_highlighting() -> R<()> {
+            let mut setup = setup()?;
+            fs::write("foo.toml.zst", compress(Compression::Zstd, b"a = 1\n")?)?;
+            setup.run(vec!["foo.toml.zst"])?;
+            assert_eq!(get_line(setup.get_section(1), 0), "toml: 1 key");
+            assert!(setup
+                .get_section(2)
+                .starts_with(&format!("1 | {} =", "a".blue().bold())));
+            Ok(())
+        }
+
+        #[test]
+        fn reports_corrupt_files() -> R<()> {
+            let mut setup = setup()?;
+            fs::write("foo.bz2", "not bzip2")?;
+            let result = setup.run(vec!["foo.bz2"]);
+            assert!(result
+                .map_err(|error| error.to_string())
+                .unwrap_err()
+                .starts_with("cannot decompress ./foo.bz2 as bzip2: "));
+            Ok(())
+        }
+    }
 }
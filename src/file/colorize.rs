@@ -1,10 +1,253 @@
 use colored::*;
 use source::Source;
+use std::path::Path;
 
 pub fn colorize(contents: Source<char>) -> Source<String> {
     Source::from(Parser { inner: contents })
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    Plain,
+    Toml,
+    Yaml,
+    /// Shell, python, ruby, perl and the like, i.e. languages with `#`
+    /// comments.
+    Script,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Language {
+        match path.extension() {
+            Some(extension) => Language::from_name(&extension.to_string_lossy()),
+            None => Language::Plain,
+        }
+    }
+
+    /// Accepts file extensions as well as the language names used for
+    /// fenced code blocks in markdown.
+    pub fn from_name(name: &str) -> Language {
+        match name.to_lowercase().as_str() {
+            "toml" => Language::Toml,
+            "yaml" | "yml" => Language::Yaml,
+            "sh" | "bash" | "zsh" | "fish" | "py" | "python" | "rb" | "ruby" | "pl" | "perl" => {
+                Language::Script
+            }
+            _ => Language::Plain,
+        }
+    }
+
+    /// Maps interpreters from shebang lines, e.g. `python3.11`, to languages.
+    pub fn from_interpreter(interpreter: &str) -> Language {
+        let name = interpreter.rsplit('/').next().unwrap_or(interpreter);
+        Language::from_name(name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+    }
+}
+
+/// Like `colorize`, but additionally highlights keys and section headers
+/// for configuration languages.
+pub fn colorize_language(contents: &str, language: Language) -> Source<String> {
+    match language {
+        Language::Plain => colorize(Source::from(
+            contents.chars().collect::<Vec<_>>().into_iter(),
+        )),
+        Language::Toml => {
+            let mut state = TomlState::default();
+            let mut result = vec![];
+            for line in contents.split_inclusive('\n') {
+                result.push(
+                    if state.array_depth > 0 || state.multiline_string.is_some() {
+                        colorize_str(line)
+                    } else {
+                        colorize_line(line, language)
+                    },
+                );
+                state.update(line);
+            }
+            Source::from(result.into_iter())
+        }
+        _ => Source::from(
+            contents
+                .split_inclusive('\n')
+                .map(|line| colorize_line(line, language))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        ),
+    }
+}
+
+fn colorize_str(string: &str) -> String {
+    colorize(Source::from(string.chars().collect::<Vec<_>>().into_iter())).join("")
+}
+
+fn colorize_line(line: &str, language: Language) -> String {
+    let mut rest = line.trim_start();
+    if language == Language::Yaml {
+        while let Some(item) = rest.strip_prefix("- ") {
+            rest = item.trim_start();
+        }
+    }
+    let indentation = &line[..line.len() - rest.len()];
+    if language == Language::Script {
+        return match comment_start(line) {
+            Some(start) => format!(
+                "{}{}{}",
+                colorize_str(&line[..start]),
+                line[start..].trim_end_matches('\n').dimmed(),
+                if line.ends_with('\n') { "\n" } else { "" }
+            ),
+            None => colorize_str(line),
+        };
+    }
+    let content = rest.trim_end();
+    if language == Language::Toml && is_toml_header(content) {
+        return format!(
+            "{}{}{}",
+            indentation,
+            content.magenta().bold(),
+            &rest[content.len()..]
+        );
+    }
+    match key_length(rest, language) {
+        Some(length) => format!(
+            "{}{}{}",
+            indentation,
+            rest[..length].blue().bold(),
+            colorize_str(&rest[length..])
+        ),
+        None => format!("{}{}", indentation, colorize_str(rest)),
+    }
+}
+
+/// Finds a `#` that starts a comment, i.e. one at the start of the line or
+/// after whitespace.
+fn comment_start(line: &str) -> Option<usize> {
+    line.char_indices()
+        .find(|(index, char)| {
+            *char == '#'
+                && line[..*index]
+                    .chars()
+                    .last()
+                    .is_none_or(char::is_whitespace)
+        })
+        .map(|(index, _)| index)
+}
+
+/// Returns the length of the key at the start of a line, e.g. `foo` in
+/// `foo = 42` (toml) or `foo: 42` (yaml).
+fn key_length(line: &str, language: Language) -> Option<usize> {
+    let end = match language {
+        Language::Toml => {
+            let length = toml_key_length(line)?;
+            return Some(length).filter(|_| line[length..].trim_start().starts_with('='));
+        }
+        Language::Yaml => line
+            .find(": ")
+            .or_else(|| line.trim_end().strip_suffix(':').map(|key| key.len()))?,
+        Language::Plain | Language::Script => return None,
+    };
+    let key = line[..end].trim_end();
+    let is_key_char = |c: char| c.is_alphanumeric() || "_-.\"' ".contains(c);
+    if key.is_empty() || !key.chars().all(is_key_char) {
+        None
+    } else {
+        Some(key.len())
+    }
+}
+
+/// Tracks what continues from one line of a toml document to the next, so
+/// that array elements and multi-line strings don't get highlighted as keys
+/// or section headers.
+#[derive(Default)]
+struct TomlState {
+    array_depth: usize,
+    multiline_string: Option<&'static str>,
+}
+
+impl TomlState {
+    fn update(&mut self, line: &str) {
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(delimiter) = self.multiline_string {
+                match rest.find(delimiter) {
+                    Some(end) => {
+                        rest = &rest[end + delimiter.len()..];
+                        self.multiline_string = None;
+                    }
+                    None => return,
+                }
+                continue;
+            }
+            let char = rest.chars().next().unwrap_or_default();
+            rest = &rest[char.len_utf8()..];
+            match char {
+                '#' => return,
+                '[' => self.array_depth += 1,
+                ']' => self.array_depth = self.array_depth.saturating_sub(1),
+                '"' | '\'' => {
+                    let triple = if char == '"' { "\"\"\"" } else { "\'\'\'" };
+                    if let Some(after) = rest.strip_prefix(&triple[1..]) {
+                        rest = after;
+                        self.multiline_string = Some(triple);
+                    } else {
+                        rest = skip_string(rest, char).unwrap_or_default();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Skips past the closing quote of a single-line string. Returns `None`
+/// for unterminated strings.
+fn skip_string(rest: &str, quote: char) -> Option<&str> {
+    let mut chars = rest.char_indices();
+    while let Some((index, char)) = chars.next() {
+        if char == '\\' && quote == '"' {
+            chars.next();
+        } else if char == quote {
+            return Some(&rest[index + 1..]);
+        }
+    }
+    None
+}
+
+/// Returns the length of the bare, quoted or dotted toml key at the start
+/// of `line`.
+fn toml_key_length(line: &str) -> Option<usize> {
+    let is_bare = |char: char| char.is_ascii_alphanumeric() || char == '_' || char == '-';
+    let mut rest = line;
+    loop {
+        let first = rest.chars().next()?;
+        rest = if first == '"' || first == '\'' {
+            skip_string(&rest[1..], first)?
+        } else if is_bare(first) {
+            rest.trim_start_matches(is_bare)
+        } else {
+            return None;
+        };
+        let end = line.len() - rest.len();
+        match rest.trim_start().strip_prefix('.') {
+            Some(after) => rest = after.trim_start(),
+            None => return Some(end),
+        }
+    }
+}
+
+/// Whether a line is a `[table]` or `[[array.of.tables]]` header.
+fn is_toml_header(content: &str) -> bool {
+    let inner = content
+        .strip_prefix("[[")
+        .and_then(|x| x.strip_suffix("]]"))
+        .or_else(|| content.strip_prefix('[').and_then(|x| x.strip_suffix(']')))
+        .map(str::trim);
+    match inner {
+        Some(inner) => toml_key_length(inner) == Some(inner.len()),
+        None => false,
+    }
+}
+
 pub struct Parser {
     inner: Source<char>,
 }
@@ -186,6 +429,134 @@ mod test {
         );
     }
 
+    mod languages {
+        use super::*;
+
+        fn test_colorize_language(input: &str, language: Language) -> String {
+            colorize_language(input, language).join("")
+        }
+
+        #[test]
+        fn highlights_toml_sections_and_keys() {
+            assert_eq!(
+                test_colorize_language("[package]\nname = \"si\"\n", Language::Toml),
+                format!(
+                    "{}\n{} = {}\n",
+                    "[package]".magenta().bold(),
+                    "name".blue().bold(),
+                    "\"si\"".yellow().bold()
+                )
+            );
+        }
+
+        #[test]
+        fn dims_comments_in_scripts() {
+            assert_eq!(
+                test_colorize_language("# foo\necho 1 # bar\necho a#b\n", Language::Script),
+                format!(
+                    "{}\necho {} {}\necho a#b\n",
+                    "# foo".dimmed(),
+                    "1".red().bold(),
+                    "# bar".dimmed()
+                )
+            );
+        }
+
+        #[test]
+        fn maps_interpreters_to_languages() {
+            assert_eq!(Language::from_interpreter("python3.11"), Language::Script);
+            assert_eq!(Language::from_interpreter("/bin/bash"), Language::Script);
+            assert_eq!(Language::from_interpreter("node"), Language::Plain);
+        }
+
+        #[test]
+        fn highlights_yaml_keys() {
+            assert_eq!(
+                test_colorize_language("foo:\n  - bar: 42\n", Language::Yaml),
+                format!(
+                    "{}:\n  - {}: {}\n",
+                    "foo".blue().bold(),
+                    "bar".blue().bold(),
+                    "42".red().bold()
+                )
+            );
+        }
+
+        #[test]
+        fn does_not_highlight_values_that_look_like_keys() {
+            assert_eq!(
+                test_colorize_language("url: http://example.com\n", Language::Yaml),
+                format!("{}: http://example.com\n", "url".blue().bold())
+            );
+            assert_eq!(
+                test_colorize_language("  \"a\", \"b\",\n", Language::Toml),
+                format!(
+                    "  {}, {},\n",
+                    "\"a\"".yellow().bold(),
+                    "\"b\"".yellow().bold()
+                )
+            );
+        }
+
+        #[test]
+        fn does_not_highlight_array_elements_as_keys() {
+            assert_eq!(
+                test_colorize_language("a = [\n  \"b = c\",\n]\n", Language::Toml),
+                format!(
+                    "{} = {}\n  {},\n{}\n",
+                    "a".blue().bold(),
+                    "[".cyan().bold(),
+                    "\"b = c\"".yellow().bold(),
+                    "]".cyan().bold()
+                )
+            );
+        }
+
+        #[test]
+        fn does_not_highlight_nested_arrays_as_section_headers() {
+            assert_eq!(
+                test_colorize_language("a = [\n  [1, 2],\n]\n[b]\n", Language::Toml),
+                format!(
+                    "{} = {}\n  {}{}, {}{},\n{}\n{}\n",
+                    "a".blue().bold(),
+                    "[".cyan().bold(),
+                    "[".cyan().bold(),
+                    "1".red().bold(),
+                    "2".red().bold(),
+                    "]".cyan().bold(),
+                    "]".cyan().bold(),
+                    "[b]".magenta().bold()
+                )
+            );
+        }
+
+        #[test]
+        fn highlights_quoted_and_dotted_toml_keys() {
+            assert_eq!(
+                test_colorize_language("\"a b\".c = 1\n[x.\"y\"]\n", Language::Toml),
+                format!(
+                    "{} = {}\n{}\n",
+                    "\"a b\".c".blue().bold(),
+                    "1".red().bold(),
+                    "[x.\"y\"]".magenta().bold()
+                )
+            );
+            assert_eq!(
+                test_colorize_language("s = \"\"\"\nx = 1\n\"\"\"\n", Language::Toml)
+                    .lines()
+                    .nth(1),
+                Some("x = 1")
+            );
+        }
+
+        #[test]
+        fn detects_languages_by_extension() {
+            assert_eq!(Language::from_path(Path::new("Cargo.toml")), Language::Toml);
+            assert_eq!(Language::from_path(Path::new("ci.YML")), Language::Yaml);
+            assert_eq!(Language::from_path(Path::new("foo.rs")), Language::Plain);
+        }
+    }
+
     mod numbers {
         use super::*;
 
@@ -193,7 +564,7 @@ mod test {
         fn colorizes_numbers() {
             assert_eq!(
                 test_colorize("foo 42 bar"),
-                format!("foo {} bar", "42".red().bold().to_string())
+                format!("foo {} bar", "42".red().bold())
             );
         }
 
@@ -201,11 +572,7 @@ mod test {
         fn works_for_numbers_at_the_end_of_lines() {
             assert_eq!(
                 test_colorize("23\n42"),
-                format!(
-                    "{}\n{}",
-                    "23".red().bold().to_string(),
-                    "42".red().bold().to_string()
-                )
+                format!("{}\n{}", "23".red().bold(), "42".red().bold())
             );
         }
 
@@ -218,11 +585,7 @@ mod test {
         fn does_colorize_numbers_within_identifiers_when_starting_with_a_digit() {
             assert_eq!(
                 test_colorize("42foo23"),
-                format!(
-                    "{}foo{}",
-                    "42".red().bold().to_string(),
-                    "23".red().bold().to_string()
-                )
+                format!("{}foo{}", "42".red().bold(), "23".red().bold())
             );
         }
     }
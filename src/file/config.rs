@@ -0,0 +1,267 @@
+use super::escapes::sanitize;
+use super::line_numbers::Location;
+use crate::utils::pluralize;
+use crate::{Context, R};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Writes a section validating and outlining a toml document. Returns the
+/// location of the parse error for invalid documents.
+pub fn output_toml(context: &mut Context, file: &Path, contents: &str) -> R<Option<Location>> {
+    let table = match contents.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(error) => {
+            let location = error
+                .span()
+                .map(|span| line_and_column(contents, span.start));
+            writeln!(
+                context.stdout,
+                "invalid toml: {}{}",
+                sanitize(
+                    &context.args,
+                    &error.message().trim_end().replace('\n', ", ")
+                ),
+                match location {
+                    Some(location) => {
+                        format!(" at line {} column {}", location.line, location.column)
+                    }
+                    None => "".to_string(),
+                }
+            )?;
+            return Ok(location);
+        }
+    };
+    if file.file_name().and_then(|name| name.to_str()) == Some("Cargo.toml") {
+        output_cargo_summary(context, &table)?;
+    }
+    writeln!(
+        context.stdout,
+        "toml: {}",
+        pluralize(table.len(), "key", "keys")
+    )?;
+    for (key, value) in &table {
+        let key = sanitize(&context.args, key);
+        let line = match value {
+            toml::Value::Table(table) => {
+                format!("[{}] {}", key, pluralize(table.len(), "key", "keys"))
+            }
+            toml::Value::Array(array)
+                if !array.is_empty() && array.iter().all(|x| x.is_table()) =>
+            {
+                format!("[[{}]] {}", key, pluralize(array.len(), "entry", "entries"))
+            }
+            value => format!("{} = {}", key, value.type_str()),
+        };
+        writeln!(context.stdout, "  {}", line)?;
+    }
+    Ok(None)
+}
+
+fn output_cargo_summary(context: &mut Context, table: &toml::Table) -> R<()> {
+    if let Some(package) = table.get("package").and_then(|x| x.as_table()) {
+        let field = |name: &str| package.get(name).and_then(|x| x.as_str()).unwrap_or("?");
+        writeln!(
+            context.stdout,
+            "package: {} {}",
+            sanitize(&context.args, field("name")),
+            sanitize(&context.args, field("version"))
+        )?;
+    }
+    let dependencies = ["dependencies", "dev-dependencies", "build-dependencies"]
+        .iter()
+        .filter_map(|section| {
+            table
+                .get(*section)
+                .and_then(|x| x.as_table())
+                .map(|dependencies| format!("{}: {}", section, dependencies.len()))
+        })
+        .collect::<Vec<_>>();
+    if !dependencies.is_empty() {
+        writeln!(context.stdout, "{}", dependencies.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Like `output_toml`, for all documents in a yaml file.
+pub fn output_yaml(context: &mut Context, contents: &str) -> R<Option<Location>> {
+    let mut documents = vec![];
+    for document in serde_yaml::Deserializer::from_str(contents) {
+        match serde_yaml::Value::deserialize(document) {
+            Ok(value) => documents.push(value),
+            Err(error) => {
+                writeln!(
+                    context.stdout,
+                    "invalid yaml: {}",
+                    sanitize(&context.args, &error.to_string())
+                )?;
+                return Ok(error.location().map(|location| Location {
+                    line: location.line(),
+                    column: location.column(),
+                }));
+            }
+        }
+    }
+    writeln!(
+        context.stdout,
+        "yaml: {}",
+        pluralize(documents.len(), "document", "documents")
+    )?;
+    for document in &documents {
+        match document {
+            serde_yaml::Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    let key = match key {
+                        serde_yaml::Value::String(key) => key.clone(),
+                        key => serde_yaml::to_string(key)?.trim_end().to_string(),
+                    };
+                    writeln!(
+                        context.stdout,
+                        "  {}: {}",
+                        sanitize(&context.args, &key),
+                        describe_yaml(value)
+                    )?;
+                }
+            }
+            value => writeln!(context.stdout, "  {}", describe_yaml(value))?,
+        }
+    }
+    Ok(None)
+}
+
+fn describe_yaml(value: &serde_yaml::Value) -> String {
+    match value {
+        serde_yaml::Value::Null => "null".to_string(),
+        serde_yaml::Value::Bool(_) => "boolean".to_string(),
+        serde_yaml::Value::Number(_) => "number".to_string(),
+        serde_yaml::Value::String(_) => "string".to_string(),
+        serde_yaml::Value::Sequence(sequence) => {
+            format!("sequence, {}", pluralize(sequence.len(), "item", "items"))
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            format!("mapping, {}", pluralize(mapping.len(), "key", "keys"))
+        }
+        serde_yaml::Value::Tagged(tagged) => describe_yaml(&tagged.value),
+    }
+}
+
+/// Converts a byte offset into 1-based line and column numbers.
+fn line_and_column(contents: &str, offset: usize) -> Location {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    Location { line, column }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn shows_an_outline_of_toml_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("config.toml"),
+            "title = \"foo\"\n\n[server]\nhost = \"localhost\"\nport = 80\n\n[[user]]\nname = \"a\"\n\n[[user]]\nname = \"b\"\n",
+        )?;
+        setup.run(vec!["config.toml"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "toml: 3 keys\n  title = string\n  [server] 2 keys\n  [[user]] 2 entries\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_toml_syntax_errors_with_their_location() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.toml"), "a = 1\nb = \n")?;
+        setup.run(vec!["foo.toml"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "invalid toml: invalid string, expected `\"`, `'` at line 2 column 5\n"
+        );
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | a = 1\n2 ! b = \n        ^\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_toml_keys() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.toml"),
+            "\"\\u001b]0;pwned\\u0007\" = 1\n",
+        )?;
+        setup.run(vec!["foo.toml"])?;
+        assert!(!setup.stdout().contains("\u{1b}]0;pwned"));
+        assert_eq!(
+            setup.get_section(1),
+            "toml: 1 key\n  ^[]0;pwned^G = integer\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn summarizes_cargo_toml() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("Cargo.toml"),
+            "[package]\nname = \"foo\"\nversion = \"1.2.3\"\n\n[dependencies]\na = \"1\"\nb = \"2\"\n\n[dev-dependencies]\nc = \"3\"\n",
+        )?;
+        setup.run(vec!["Cargo.toml"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "package: foo 1.2.3\ndependencies: 2, dev-dependencies: 1\ntoml: 3 keys\n  [package] 2 keys\n  [dependencies] 2 keys\n  [dev-dependencies] 1 key\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_an_outline_of_yaml_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("ci.yml"),
+            "name: ci\non:\n  push: {}\njobs:\n  - a\n  - b\n",
+        )?;
+        setup.run(vec!["ci.yml"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "yaml: 1 document\n  name: string\n  on: mapping, 1 key\n  jobs: sequence, 2 items\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_yaml_keys() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.yaml"), "\"\\e[2J\": 1\n")?;
+        setup.run(vec!["foo.yaml"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert_eq!(setup.get_section(1), "yaml: 1 document\n  ^[[2J: number\n");
+        Ok(())
+    }
+
+    #[test]
+    fn reports_yaml_syntax_errors_with_their_location() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.yaml"), "a: 1\n b: 2\n")?;
+        setup.run(vec!["foo.yaml"])?;
+        assert!(setup.get_section(1).starts_with(
+            "invalid yaml: mapping values are not allowed in this context at line 2 column"
+        ));
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | a: 1\n2 !  b: 2\n      ^\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use super::colorize::{colorize_language, Language};
+use super::escapes;
+use super::line_numbers::{max_number_length, pad};
+use super::{detect_encoding, is_text};
+use crate::utils::render_path;
+use crate::{write_separator, Context, R};
+use colored::*;
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::path::Path;
+
+const CONTEXT_RADIUS: usize = 3;
+
+pub fn output(context: &mut Context, old: &Path, new: &Path) -> R<()> {
+    let (old_bytes, new_bytes) = (fs::read(old)?, fs::read(new)?);
+    writeln!(
+        context.stdout,
+        "diff: {} → {}",
+        render_path(old),
+        render_path(new)
+    )?;
+    write_separator(context)?;
+    if old_bytes == new_bytes {
+        writeln!(context.stdout, "files are identical")?;
+        return Ok(());
+    }
+    let (old, new) = match (
+        Side::read(context, old, &old_bytes),
+        Side::read(context, new, &new_bytes),
+    ) {
+        (Some(old), Some(new)) => (old, new),
+        _ => {
+            writeln!(context.stdout, "binary files differ")?;
+            return Ok(());
+        }
+    };
+    let diff = TextDiff::from_lines(&old.contents, &new.contents);
+    let max_number_length = max_number_length(&old.contents).max(max_number_length(&new.contents));
+    let blank = " ".repeat(max_number_length);
+    for hunk in diff
+        .unified_diff()
+        .context_radius(CONTEXT_RADIUS)
+        .iter_hunks()
+    {
+        writeln!(context.stdout, "{}", hunk.header().to_string().cyan())?;
+        for change in hunk.iter_changes() {
+            let number = |index: Option<usize>| match index {
+                Some(index) => pad(max_number_length, index as i32 + 1),
+                None => blank.clone(),
+            };
+            let (sign, line) = match change.tag() {
+                ChangeTag::Equal => ("|".normal(), new.line(change.new_index())),
+                ChangeTag::Delete => ("-".red(), old.line(change.old_index())),
+                ChangeTag::Insert => ("+".green(), new.line(change.new_index())),
+            };
+            writeln!(
+                context.stdout,
+                "{} {} {}{}",
+                number(change.old_index()),
+                number(change.new_index()),
+                sign,
+                if line.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" {}", line)
+                }
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// One of the diffed files, decoded and highlighted like when it is shown
+/// on its own.
+struct Side {
+    contents: String,
+    lines: Vec<String>,
+}
+
+impl Side {
+    /// Returns `None` for binary files.
+    fn read(context: &Context, file: &Path, bytes: &[u8]) -> Option<Side> {
+        let encoding = detect_encoding(context, bytes);
+        if !is_text(bytes, encoding) {
+            return None;
+        }
+        let contents = escapes::sanitize(&context.args, &encoding.decode(bytes)).into_owned();
+        // Colors would break up escape sequences that are passed through.
+        let rendered = if context.args.ansi {
+            contents.clone()
+        } else {
+            colorize_language(&contents, Language::from_path(file)).join("")
+        };
+        let lines = rendered
+            .split_inclusive('\n')
+            .map(|line| line.trim_end_matches('\n').to_string())
+            .collect();
+        Some(Side { contents, lines })
+    }
+
+    fn line(&self, index: Option<usize>) -> &str {
+        index
+            .and_then(|index| self.lines.get(index))
+            .map_or("", |line| line.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::R;
+    use colored::*;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn shows_a_unified_diff_with_line_numbers() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a"), "foo\nbar\nbaz\n")?;
+        fs::write(setup.tempdir().join("b"), "foo\nqux\nbaz\n\n")?;
+        setup.run(vec!["a", "b", "--diff"])?;
+        assert_eq!(setup.get_section(0), "diff: ./a → ./b\n");
+        assert_eq!(
+            setup.get_section(1),
+            format!(
+                "{}\n1 1 | foo\n2   {} bar\n  2 {} qux\n3 3 | baz\n  4 {}\n",
+                "@@ -1,3 +1,4 @@".cyan(),
+                "-".red(),
+                "+".green(),
+                "+".green()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn colorizes_the_diffed_lines() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a"), "foo\n")?;
+        fs::write(setup.tempdir().join("b"), "(foo)\n")?;
+        setup.run(vec!["a", "b", "--diff"])?;
+        assert_eq!(
+            get_line(setup.get_section(1), 2),
+            format!(
+                "  1 {} {}foo{}",
+                "+".green(),
+                "(".cyan().bold(),
+                ")".cyan().bold()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn highlights_lines_by_language() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a.toml"), "a = 1\n")?;
+        fs::write(setup.tempdir().join("b.toml"), "a = 2\n")?;
+        setup.run(vec!["a.toml", "b.toml", "--diff"])?;
+        assert_eq!(
+            get_line(setup.get_section(1), 2),
+            format!("  1 {} {} = {}", "+".green(), "a".blue().bold(), "2".cyan())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_both_sides() -> R<()> {
+        let mut setup = setup()?;
+        let utf16 = |text: &str| {
+            let mut bytes = vec![0xff, 0xfe];
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            }
+            bytes
+        };
+        fs::write(setup.tempdir().join("a"), utf16("foo\nbar\n"))?;
+        fs::write(setup.tempdir().join("b"), utf16("foo\nbaz\n"))?;
+        setup.run(vec!["a", "b", "--diff"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(1))?)?,
+            "@@ -1,2 +1,2 @@\n1 1 | foo\n2   - bar\n  2 + baz\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_differing_binary_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a"), b"foo\n")?;
+        fs::write(setup.tempdir().join("b"), b"\x89PNG\r\n\x1a\n\0\0")?;
+        setup.run(vec!["a", "b", "--diff"])?;
+        assert_eq!(setup.get_section(1), "binary files differ\n");
+        Ok(())
+    }
+
+    #[test]
+    fn reports_identical_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a"), "foo\n")?;
+        fs::write(setup.tempdir().join("b"), "foo\n")?;
+        setup.run(vec!["a", "b", "--diff"])?;
+        assert_eq!(setup.get_section(1), "files are identical\n");
+        Ok(())
+    }
+
+    #[test]
+    fn passes_escape_sequences_through_with_ansi() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("a"), "foo\n")?;
+        fs::write(setup.tempdir().join("b"), "\u{1b}[31m(foo)\u{1b}[0m\n")?;
+        setup.run(vec!["a", "b", "--diff", "--ansi"])?;
+        assert_eq!(
+            get_line(setup.get_section(1), 2),
+            format!("  1 {} \u{1b}[31m(foo)\u{1b}[0m", "+".green())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Text {
+        encoding: &'static encoding_rs::Encoding,
+        bom: bool,
+    },
+    Binary,
+}
+
+impl Encoding {
+    /// Detects encodings by byte order marks first. Without one, files that
+    /// look like UTF-16 are treated as such, other files containing NUL
+    /// bytes are binary, and files that aren't valid UTF-8 get their
+    /// encoding guessed from byte frequencies.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+            Encoding::Text {
+                encoding,
+                bom: true,
+            }
+        } else if let Some(encoding) = detect_utf16_without_bom(bytes) {
+            Encoding::Text {
+                encoding,
+                bom: false,
+            }
+        } else if bytes.contains(&0) {
+            Encoding::Binary
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Encoding::Text {
+                encoding: UTF_8,
+                bom: false,
+            }
+        } else {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(bytes, true);
+            Encoding::Text {
+                encoding: detector.guess(None, true),
+                bom: false,
+            }
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Encoding::Text {
+                encoding,
+                bom: true,
+            } if encoding == UTF_8 => "utf-8 with bom".to_string(),
+            Encoding::Text { encoding, .. } if encoding == WINDOWS_1252 => "latin-1".to_string(),
+            Encoding::Text { encoding, .. } => encoding.name().to_lowercase(),
+            Encoding::Binary => "binary".to_string(),
+        }
+    }
+
+    /// Transcodes to UTF-8, dropping a byte order mark.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Text { encoding, .. } => {
+                encoding.decode_with_bom_removal(bytes).0.into_owned()
+            }
+            Encoding::Binary => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+/// Mostly ASCII text in UTF-16 has a NUL in every other byte, next to a
+/// printable character.
+fn detect_utf16_without_bom(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let looks_like = |zero_offset: usize| {
+        let mut pairs = bytes.chunks_exact(2);
+        // Binary files usually fail this early on.
+        if pairs.any(|pair| pair[1 - zero_offset] == 0) {
+            return false;
+        }
+        let ascii = bytes
+            .chunks_exact(2)
+            .filter(|pair| pair[zero_offset] == 0)
+            .map(|pair| pair[1 - zero_offset])
+            .filter(|byte| byte.is_ascii_graphic() || b" \t\r\n".contains(byte))
+            .count();
+        ascii * 2 > bytes.len() / 2
+    };
+    if looks_like(1) {
+        Some(UTF_16LE)
+    } else if looks_like(0) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+pub fn line_endings(text: &str) -> Option<&'static str> {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    match (lf, crlf) {
+        (0, 0) => None,
+        (_, 0) => Some("lf"),
+        (0, _) => Some("crlf"),
+        _ => Some("mixed"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+
+    fn detect_name(bytes: &[u8]) -> String {
+        Encoding::detect(bytes).name()
+    }
+
+    #[test]
+    fn detects_encodings() {
+        assert_eq!(detect_name(b"foo"), "utf-8");
+        assert_eq!(detect_name("föö".as_bytes()), "utf-8");
+        assert_eq!(detect_name(b"\xef\xbb\xbffoo"), "utf-8 with bom");
+        assert_eq!(detect_name(b"\xff\xfef\x00"), "utf-16le");
+        assert_eq!(detect_name(b"\xfe\xff\x00f"), "utf-16be");
+        assert_eq!(detect_name(b"Gr\xfc\xdfe aus K\xf6ln"), "latin-1");
+        assert_eq!(detect_name(b"\x00\x01"), "binary");
+    }
+
+    #[test]
+    fn detects_utf_16_without_byte_order_marks() {
+        assert_eq!(detect_name(b"f\x00o\x00o\x00\n\x00"), "utf-16le");
+        assert_eq!(detect_name(b"\x00f\x00o\x00o\x00\n"), "utf-16be");
+        assert_eq!(detect_name(b"\x00\x00\x01\x02"), "binary");
+    }
+
+    #[test]
+    fn decodes_text() {
+        let decode = |bytes: &[u8]| Encoding::detect(bytes).decode(bytes);
+        assert_eq!(decode(b"\xff\xfef\x00\xf6\x00"), "fö");
+        assert_eq!(decode(b"\xfe\xff\x00f\x00\xf6"), "fö");
+        assert_eq!(decode(b"\xef\xbb\xbff"), "f");
+    }
+
+    #[test]
+    fn classifies_line_endings() {
+        assert_eq!(line_endings("foo"), None);
+        assert_eq!(line_endings("a\nb\n"), Some("lf"));
+        assert_eq!(line_endings("a\r\nb\r\n"), Some("crlf"));
+        assert_eq!(line_endings("a\r\nb\n"), Some("mixed"));
+    }
+
+    #[test]
+    fn shows_transcoded_contents() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", b"\xff\xfea\x00\n\x00b\x00\n\x00")?;
+        setup.run(vec!["foo"])?;
+        assert_eq!(setup.get_section(1), "1 | a\n2 | b\n");
+        Ok(())
+    }
+
+    #[test]
+    fn can_be_overridden() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", b"\xcf\xf0\xe8\xe2\xe5\xf2\n")?;
+        setup.run(vec!["--encoding", "windows-1251", "foo"])?;
+        assert_eq!(
+            get_line(setup.get_section(0), 1),
+            "type: text/plain, windows-1251, lf line endings"
+        );
+        assert_eq!(setup.get_section(1), "1 | Привет\n");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_encodings() -> R<()> {
+        let mut setup = setup()?;
+        let result = setup.run(vec!["--encoding", "foo", "."]);
+        assert_eq!(
+            result.map_err(|x| x.to_string()),
+            Err("unknown encoding: foo\n".to_string())
+        );
+        Ok(())
+    }
+}
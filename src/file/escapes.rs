@@ -0,0 +1,99 @@
+use crate::args::Args;
+use std::borrow::Cow;
+
+/// Neutralizes a string taken from a file before it's written, unless
+/// `--ansi` is given.
+pub fn sanitize<'a>(args: &Args, text: &'a str) -> Cow<'a, str> {
+    if args.ansi || !text.contains(is_escape) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(neutralize(text))
+    }
+}
+
+/// Makes control characters in untrusted contents harmless, so they can't
+/// retitle the terminal, move the cursor, overwrite or hide text. C0
+/// controls and `DEL` are shown in caret notation, e.g. `ESC` as `^[`, and
+/// the 8-bit C1 control codes, which some terminals also interpret, as code
+/// points. Newlines, tabs and the carriage returns of CRLF line endings are
+/// kept.
+pub fn neutralize(contents: &str) -> String {
+    if !contents.contains(is_escape) {
+        return contents.to_string();
+    }
+    let mut result = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\r' if chars.peek() == Some(&'\n') => result.push(char),
+            '\u{0}'..='\u{1f}' | '\u{7f}' if is_escape(char) => {
+                result.push('^');
+                result.push((char as u8 ^ 0x40) as char);
+            }
+            char if is_escape(char) => result.push_str(&format!("<U+{:04X}>", char as u32)),
+            char => result.push(char),
+        }
+    }
+    result
+}
+
+/// Whether a character may be interpreted by the terminal. Carriage returns
+/// are always included and only kept by `neutralize` before newlines.
+fn is_escape(char: char) -> bool {
+    match char {
+        '\n' | '\t' => false,
+        char => char.is_control(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn neutralizes_escape_sequences() {
+        assert_eq!(neutralize("foo"), "foo");
+        assert_eq!(neutralize("\u{1b}[31mred\u{1b}[0m"), "^[[31mred^[[0m");
+        assert_eq!(neutralize("\u{1b}]0;title\u{7}"), "^[]0;title^G");
+        assert_eq!(neutralize("\u{9b}2J"), "<U+009B>2J");
+        assert_eq!(neutralize("a\rb\u{8}\u{7}\u{7f}"), "a^Mb^H^G^?");
+        assert_eq!(neutralize("a\tb\r\nc\n"), "a\tb\r\nc\n");
+    }
+
+    #[test]
+    fn neutralizes_file_contents_by_default() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", "\u{1b}[2Jfoo\n")?;
+        setup.run(vec!["foo"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(1))?)?,
+            "1 | ^[[2Jfoo\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn passes_escape_sequences_through_with_a_flag() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", "\u{1b}[31m(red)\u{1b}[0m\n")?;
+        setup.run(vec!["--ansi", "foo"])?;
+        assert_eq!(setup.get_section(1), "1 | \u{1b}[31m(red)\u{1b}[0m\n");
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_diffs() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("a", "foo\n")?;
+        fs::write("b", "\u{1b}[2Jfoo\n")?;
+        setup.run(vec!["--diff", "a", "b"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert!(String::from_utf8(strip(setup.stdout())?)?.contains("^[[2Jfoo"));
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use super::escapes::sanitize;
+use crate::utils::render_size;
+use crate::{Context, R};
+use goblin::elf::header::{self, ET_CORE, ET_DYN, ET_EXEC, ET_REL};
+use goblin::elf::Elf;
+use std::borrow::Cow;
+
+pub fn is_elf(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\x7fELF")
+}
+
+/// Writes a summary of an ELF binary instead of its contents.
+pub fn output_elf(context: &mut Context, bytes: &[u8]) -> R<()> {
+    let elf = match Elf::parse(bytes) {
+        Ok(elf) => elf,
+        Err(error) => {
+            writeln!(context.stdout, "invalid elf: {}", error)?;
+            return Ok(());
+        }
+    };
+    writeln!(
+        context.stdout,
+        "elf: {}-bit {}-endian, {}, {}",
+        if elf.is_64 { 64 } else { 32 },
+        if elf.little_endian { "little" } else { "big" },
+        header::machine_to_str(elf.header.e_machine).to_lowercase(),
+        render_type(&elf)
+    )?;
+    writeln!(context.stdout, "entry point: {:#x}", elf.entry)?;
+    if let Some(interpreter) = elf.interpreter {
+        writeln!(
+            context.stdout,
+            "interpreter: {}",
+            sanitize(&context.args, interpreter)
+        )?;
+    }
+    writeln!(
+        context.stdout,
+        "shared libraries: {}",
+        if elf.libraries.is_empty() {
+            "none".to_string()
+        } else {
+            sanitize(&context.args, &elf.libraries.join(", ")).into_owned()
+        }
+    )?;
+    writeln!(
+        context.stdout,
+        "stripped: {}",
+        if elf.syms.is_empty() { "yes" } else { "no" }
+    )?;
+    let sections: Vec<(Cow<str>, u64)> = elf
+        .section_headers
+        .iter()
+        .filter_map(|section| {
+            let name = elf.shdr_strtab.get_at(section.sh_name)?;
+            Some((sanitize(&context.args, name), section.sh_size)).filter(|_| !name.is_empty())
+        })
+        .collect();
+    if !sections.is_empty() {
+        writeln!(context.stdout, "sections:")?;
+        let width = sections
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (name, size) in sections {
+            writeln!(
+                context.stdout,
+                "  {:width$} {}",
+                name,
+                render_size(size),
+                width = width
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn render_type(elf: &Elf) -> String {
+    match elf.header.e_type {
+        ET_EXEC => "executable",
+        ET_DYN if elf.interpreter.is_some() => "position-independent executable",
+        ET_DYN => "shared object",
+        ET_REL => "relocatable",
+        ET_CORE => "core dump",
+        other => return header::et_to_str(other).to_lowercase(),
+    }
+    .to_string()
+}
+
+/// Returns the interpreter named in a shebang line. For `#!/usr/bin/env`
+/// that's the program that `env` runs.
+pub fn interpreter(contents: &str) -> Option<String> {
+    let line = contents.strip_prefix("#!")?.lines().next()?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?;
+    if program.rsplit('/').next() == Some("env") {
+        words
+            .find(|word| !word.starts_with('-'))
+            .map(|word| word.to_string())
+    } else {
+        Some(program.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use colored::*;
+    use std::fs;
+
+    /// A minimal 64-bit little-endian x86-64 ELF file without program or
+    /// section headers.
+    fn elf_header(e_type: u16, entry: u64) -> Vec<u8> {
+        let mut result = b"\x7fELF\x02\x01\x01".to_vec();
+        result.resize(16, 0);
+        result.extend_from_slice(&e_type.to_le_bytes());
+        result.extend_from_slice(&header::EM_X86_64.to_le_bytes());
+        result.extend_from_slice(&1u32.to_le_bytes());
+        result.extend_from_slice(&entry.to_le_bytes());
+        result.extend_from_slice(&[0; 8 + 8 + 4]);
+        for field in [64u16, 56, 0, 64, 0, 0] {
+            result.extend_from_slice(&field.to_le_bytes());
+        }
+        result
+    }
+
+    #[test]
+    fn summarizes_elf_headers() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", elf_header(ET_EXEC, 0x401000))?;
+        setup.run(vec!["foo"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "elf: 64-bit little-endian, x86_64, executable\n\
+             entry point: 0x401000\n\
+             shared libraries: none\n\
+             stripped: yes\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn lists_libraries_and_sections_of_real_binaries() -> R<()> {
+        let mut setup = setup()?;
+        setup.run(vec![std::env::current_exe()?.to_string_lossy()])?;
+        let summary = setup.get_section(1);
+        assert!(summary.contains("\nstripped: no\n"));
+        assert!(summary.contains("\n  .text "));
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            assert!(summary.contains("libc.so"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_section_names() -> R<()> {
+        let mut setup = setup()?;
+        let mut elf = elf_header(ET_EXEC, 0x401000);
+        let names = b"\0\x1b[2J\0\0";
+        let section_headers = (elf.len() + names.len()) as u64;
+        elf[40..48].copy_from_slice(&section_headers.to_le_bytes());
+        elf[60..62].copy_from_slice(&2u16.to_le_bytes());
+        elf[62..64].copy_from_slice(&1u16.to_le_bytes());
+        let strtab_offset = elf.len() as u64;
+        elf.extend_from_slice(names);
+        elf.extend_from_slice(&[0; 64]);
+        elf.extend_from_slice(&1u32.to_le_bytes());
+        elf.extend_from_slice(&3u32.to_le_bytes());
+        elf.extend_from_slice(&[0; 16]);
+        elf.extend_from_slice(&strtab_offset.to_le_bytes());
+        elf.extend_from_slice(&(names.len() as u64).to_le_bytes());
+        elf.extend_from_slice(&[0; 24]);
+        fs::write("foo", elf)?;
+        setup.run(vec!["foo"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert!(setup.get_section(1).ends_with("sections:\n  ^[[2J 7\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn reports_broken_elf_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", b"\x7fELF\x02")?;
+        setup.run(vec!["foo"])?;
+        assert!(setup.get_section(1).starts_with("invalid elf: "));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_interpreters_in_shebang_lines() {
+        assert_eq!(interpreter("#!/bin/sh\n"), Some("/bin/sh".to_string()));
+        assert_eq!(
+            interpreter("#!/usr/bin/env -S python3 -u\n"),
+            Some("python3".to_string())
+        );
+        assert_eq!(interpreter("# not a shebang\n"), None);
+    }
+
+    #[test]
+    fn highlights_scripts_by_their_interpreter() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", "#!/usr/bin/env python3\nprint(1) # comment\n")?;
+        setup.run(vec!["foo"])?;
+        assert_eq!(setup.get_section(1), "interpreter: python3\n");
+        assert_eq!(
+            get_line(setup.get_section(2), 1),
+            format!(
+                "2 | print{}{}{} {}",
+                "(".cyan().bold(),
+                "1".red().bold(),
+                ")".cyan().bold(),
+                "# comment".dimmed()
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,455 @@
+use super::escapes::sanitize;
+use super::has_extension;
+use crate::{Context, R};
+use exif::{In, Tag, Value};
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageDecoder, ImageFormat, ImageReader, Rgba};
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Raster(ImageFormat),
+    Svg,
+}
+
+impl Format {
+    /// Raster formats are detected by their magic bytes, svg by extension.
+    pub fn detect(file: &Path, bytes: &[u8]) -> Option<Format> {
+        match image::guess_format(bytes) {
+            Ok(
+                format @ (ImageFormat::Png
+                | ImageFormat::Jpeg
+                | ImageFormat::Gif
+                | ImageFormat::WebP),
+            ) => Some(Format::Raster(format)),
+            _ if has_extension(file, &["svg"]) => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a summary of the image instead of its contents.
+pub fn output(context: &mut Context, format: Format, bytes: &[u8]) -> R<()> {
+    match format {
+        Format::Raster(format) => output_raster(context, format, bytes),
+        Format::Svg => output_svg(context, &String::from_utf8_lossy(bytes)),
+    }
+}
+
+fn output_raster(context: &mut Context, format: ImageFormat, bytes: &[u8]) -> R<()> {
+    let name = format!("{:?}", format).to_lowercase();
+    let mut decoder = match ImageReader::with_format(Cursor::new(bytes), format).into_decoder() {
+        Ok(decoder) => decoder,
+        Err(error) => {
+            writeln!(context.stdout, "invalid {}: {}", name, error)?;
+            return Ok(());
+        }
+    };
+    let (width, height) = decoder.dimensions();
+    writeln!(
+        context.stdout,
+        "image: {}, {}×{} pixels",
+        name, width, height
+    )?;
+    writeln!(
+        context.stdout,
+        "color: {}",
+        render_color_type(decoder.original_color_type())
+    )?;
+    if let Ok(Some(exif)) = decoder.exif_metadata() {
+        output_exif(context, exif)?;
+    }
+    Ok(())
+}
+
+/// Renders the image with half-block characters, two pixels per character,
+/// using truecolor escape sequences. Images get scaled down to fit the
+/// terminal width, but never scaled up.
+pub fn output_preview(context: &mut Context, format: ImageFormat, bytes: &[u8]) -> R<()> {
+    let image = match decode(format, bytes) {
+        Ok(image) => image,
+        Err(_) => return Ok(()),
+    };
+    let columns = image
+        .width()
+        .min(context.terminal_width.unwrap_or(80) as u32)
+        .max(1);
+    let rows = (image.height() as u64 * columns as u64 / image.width().max(1) as u64).max(1);
+    let image = image
+        .resize_exact(columns, rows as u32, FilterType::Triangle)
+        .into_rgba8();
+    for y in (0..image.height()).step_by(2) {
+        let mut line = String::new();
+        for x in 0..image.width() {
+            let upper = image.get_pixel(x, y);
+            let lower = if y + 1 < image.height() {
+                Some(image.get_pixel(x, y + 1))
+            } else {
+                None
+            };
+            line.push_str(&render_half_blocks(upper, lower));
+        }
+        writeln!(context.stdout, "{}\x1b[0m", line)?;
+    }
+    Ok(())
+}
+
+fn decode(format: ImageFormat, bytes: &[u8]) -> R<DynamicImage> {
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format).into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Mostly transparent pixels are left blank, so that the terminal
+/// background shows through.
+fn render_half_blocks(upper: &Rgba<u8>, lower: Option<&Rgba<u8>>) -> String {
+    let visible = |pixel: &&Rgba<u8>| pixel[3] >= 128;
+    let color = |layer: u8, pixel: &Rgba<u8>| {
+        format!("\x1b[{};2;{};{};{}m", layer, pixel[0], pixel[1], pixel[2])
+    };
+    match (Some(upper).filter(visible), lower.filter(visible)) {
+        (Some(upper), Some(lower)) => format!("{}{}▀", color(38, upper), color(48, lower)),
+        (Some(upper), None) => format!("\x1b[0m{}▀", color(38, upper)),
+        (None, Some(lower)) => format!("\x1b[0m{}▄", color(38, lower)),
+        (None, None) => "\x1b[0m ".to_string(),
+    }
+}
+
+fn render_color_type(color_type: ExtendedColorType) -> String {
+    use ExtendedColorType::*;
+    let name = match color_type {
+        A8 => "alpha",
+        L1 | L2 | L4 | L8 | L16 => "grayscale",
+        La1 | La2 | La4 | La8 | La16 => "grayscale with alpha",
+        Rgb1 | Rgb2 | Rgb4 | Rgb5x1 | Rgb8 | Rgb16 | Bgr8 | Rgb32F => "rgb",
+        Rgba1 | Rgba2 | Rgba4 | Rgba8 | Rgba16 | Bgra8 | Rgba32F => "rgba",
+        Cmyk8 | Cmyk16 => "cmyk",
+        _ => return format!("{:?}", color_type),
+    };
+    format!(
+        "{}, {} bits per channel",
+        name,
+        color_type.bits_per_pixel() / color_type.channel_count() as u16
+    )
+}
+
+/// Writes the orientation and camera data, skipping fields that are not
+/// present.
+fn output_exif(context: &mut Context, raw: Vec<u8>) -> R<()> {
+    let exif = match exif::Reader::new().read_raw(raw) {
+        Ok(exif) => exif,
+        Err(_) => return Ok(()),
+    };
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+    if let Some(orientation) = field(Tag::Orientation).and_then(|field| field.value.get_uint(0)) {
+        writeln!(
+            context.stdout,
+            "orientation: {}",
+            render_orientation(orientation)
+        )?;
+    }
+    let ascii = |tag| match field(tag).map(|field| &field.value) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string()),
+        _ => None,
+    };
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        (Some(make), Some(model)) if !model.starts_with(&make) => {
+            Some(format!("{} {}", make, model))
+        }
+        (_, Some(model)) => Some(model),
+        (make, None) => make,
+    };
+    if let Some(camera) = camera {
+        writeln!(
+            context.stdout,
+            "camera: {}",
+            sanitize(&context.args, &camera)
+        )?;
+    }
+    if let Some(date) = ascii(Tag::DateTimeOriginal) {
+        writeln!(context.stdout, "taken: {}", sanitize(&context.args, &date))?;
+    }
+    let settings: Vec<String> = [
+        Tag::ExposureTime,
+        Tag::FNumber,
+        Tag::PhotographicSensitivity,
+        Tag::FocalLength,
+    ]
+    .iter()
+    .filter_map(|tag| field(*tag))
+    .map(|field| field.display_value().with_unit(&exif).to_string())
+    .collect();
+    if !settings.is_empty() {
+        writeln!(context.stdout, "settings: {}", settings.join(", "))?;
+    }
+    Ok(())
+}
+
+fn render_orientation(orientation: u32) -> String {
+    match orientation {
+        1 => "normal",
+        2 => "mirrored horizontally",
+        3 => "rotated 180°",
+        4 => "mirrored vertically",
+        5 => "mirrored horizontally, rotated 270° clockwise",
+        6 => "rotated 90° clockwise",
+        7 => "mirrored horizontally, rotated 90° clockwise",
+        8 => "rotated 270° clockwise",
+        _ => return format!("unknown ({})", orientation),
+    }
+    .to_string()
+}
+
+fn output_svg(context: &mut Context, contents: &str) -> R<()> {
+    let tag = match contents.find("<svg") {
+        Some(start) => {
+            let tag = &contents[start..];
+            &tag[..tag.find('>').unwrap_or(tag.len())]
+        }
+        None => {
+            writeln!(context.stdout, "invalid svg: no <svg> element")?;
+            return Ok(());
+        }
+    };
+    match (attribute(tag, "width"), attribute(tag, "height")) {
+        (Some(width), Some(height)) => writeln!(
+            context.stdout,
+            "image: svg, {}×{}",
+            sanitize(&context.args, width),
+            sanitize(&context.args, height)
+        )?,
+        _ => writeln!(context.stdout, "image: svg")?,
+    }
+    if let Some(view_box) = attribute(tag, "viewBox") {
+        writeln!(
+            context.stdout,
+            "view box: {}",
+            sanitize(&context.args, view_box)
+        )?;
+    }
+    Ok(())
+}
+
+/// Finds the value of an attribute in the text of an xml start tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(index) = rest.find(name) {
+        let preceded_by_space = rest[..index]
+            .chars()
+            .last()
+            .is_some_and(char::is_whitespace);
+        rest = &rest[index + name.len()..];
+        let value = rest.trim_start().strip_prefix('=').map(str::trim_start);
+        if let (true, Some(value)) = (preceded_by_space, value) {
+            let quote = value.chars().next()?;
+            if quote == '"' || quote == '\'' {
+                let value = &value[1..];
+                return value.find(quote).map(|end| &value[..end]);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use exif::Field;
+    use std::fs;
+
+    /// Inserts an `eXIf` chunk with the given fields right after the `IHDR`
+    /// chunk of a png file.
+    fn add_exif(png: &[u8], fields: &[Field]) -> R<Vec<u8>> {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut exif = Cursor::new(vec![]);
+        writer.write(&mut exif, false)?;
+        let exif = exif.into_inner();
+        let mut chunk = (exif.len() as u32).to_be_bytes().to_vec();
+        let mut crc = flate2::Crc::new();
+        crc.update(b"eXIf");
+        crc.update(&exif);
+        chunk.extend_from_slice(b"eXIf");
+        chunk.extend_from_slice(&exif);
+        chunk.extend_from_slice(&crc.sum().to_be_bytes());
+        let ihdr_end = 8 + 4 + 4 + 13 + 4;
+        Ok([&png[..ihdr_end], &chunk, &png[ihdr_end..]].concat())
+    }
+
+    #[test]
+    fn shows_a_summary_of_png_files() -> R<()> {
+        let mut setup = setup()?;
+        image::RgbaImage::new(3, 2).save("foo.png")?;
+        setup.run(vec!["foo.png"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "image: png, 3×2 pixels\ncolor: rgba, 8 bits per channel\n"
+        );
+        assert_eq!(setup.stdout().matches("1 |").count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_images_by_their_contents() -> R<()> {
+        let mut setup = setup()?;
+        image::RgbaImage::new(4, 4).save_with_format("foo", ImageFormat::Gif)?;
+        setup.run(vec!["foo"])?;
+        assert_eq!(get_line(setup.get_section(1), 0), "image: gif, 4×4 pixels");
+        Ok(())
+    }
+
+    #[test]
+    fn shows_exif_orientation_and_camera_data() -> R<()> {
+        let mut setup = setup()?;
+        let mut png = Cursor::new(vec![]);
+        image::RgbImage::new(1, 1).write_to(&mut png, ImageFormat::Png)?;
+        let ascii = |tag, value: &str| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        };
+        let png = add_exif(
+            png.get_ref(),
+            &[
+                Field {
+                    tag: Tag::Orientation,
+                    ifd_num: In::PRIMARY,
+                    value: Value::Short(vec![6]),
+                },
+                ascii(Tag::Make, "Canon"),
+                ascii(Tag::Model, "Canon EOS 5D"),
+            ],
+        )?;
+        fs::write("foo.png", png)?;
+        setup.run(vec!["foo.png"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "image: png, 1×1 pixels\ncolor: rgb, 8 bits per channel\norientation: rotated 90° clockwise\ncamera: Canon EOS 5D\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_exif_data() -> R<()> {
+        let mut setup = setup()?;
+        let mut png = Cursor::new(vec![]);
+        image::RgbImage::new(1, 1).write_to(&mut png, ImageFormat::Png)?;
+        let ascii = |tag, value: &str| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        };
+        let png = add_exif(
+            png.get_ref(),
+            &[
+                ascii(Tag::Make, "\u{1b}[2J"),
+                ascii(Tag::Model, "\u{1b}]0;pwned\u{7}"),
+            ],
+        )?;
+        fs::write("foo.png", png)?;
+        setup.run(vec!["foo.png"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert_eq!(
+            get_line(setup.get_section(1), 2),
+            "camera: ^[[2J ^[]0;pwned^G"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn renders_previews_with_half_blocks() -> R<()> {
+        let mut setup = setup()?;
+        let mut image = image::RgbaImage::new(2, 3);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        image.put_pixel(1, 1, Rgba([0, 255, 0, 255]));
+        image.put_pixel(0, 2, Rgba([1, 2, 3, 255]));
+        image.save("foo.png")?;
+        setup.run(vec!["--preview", "foo.png"])?;
+        assert_eq!(
+            setup.get_section(2),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\x1b[38;2;0;255;0m▄\x1b[0m\n\
+             \x1b[0m\x1b[38;2;1;2;3m▀\x1b[0m \x1b[0m\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn scales_previews_down_to_the_terminal_width() -> R<()> {
+        let mut setup = setup()?;
+        image::RgbaImage::from_pixel(200, 100, Rgba([0, 0, 0, 255])).save("foo.png")?;
+        setup.run(vec!["--preview", "foo.png"])?;
+        let preview = setup.get_section(2);
+        assert_eq!(preview.lines().count(), 13);
+        assert_eq!(
+            preview.lines().next().unwrap().matches('▀').count(),
+            TEST_TERMINAL_WIDTH.unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_broken_images() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo.png", b"\x89PNG\r\n\x1a\n")?;
+        setup.run(vec!["foo.png"])?;
+        assert!(setup.get_section(1).starts_with("invalid png: "));
+        Ok(())
+    }
+
+    #[test]
+    fn summarizes_svg_files() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            "foo.svg",
+            "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height='50px' viewBox=\"0 0 100 50\">\n</svg>\n",
+        )?;
+        setup.run(vec!["foo.svg"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "image: svg, 100×50px\nview box: 0 0 100 50\n"
+        );
+        assert_eq!(setup.stdout().matches("1 |").count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_svg_attributes() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            "foo.svg",
+            "<svg width=\"\u{1b}[2J\" height=\"2\" viewBox=\"\u{9b}2J\">\n",
+        )?;
+        setup.run(vec!["foo.svg"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert_eq!(
+            setup.get_section(1),
+            "image: svg, ^[[2J×2\nview box: <U+009B>2J\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_svg_source_with_raw() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo.svg", "<svg width=\"1\" height=\"2\"/>\n")?;
+        setup.run(vec!["--raw", "foo.svg"])?;
+        assert_eq!(setup.get_section(1), "image: svg, 1×2\n");
+        assert!(setup.get_section(2).starts_with("1 | "));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_attributes_in_start_tags() {
+        assert_eq!(attribute("<svg a=\"1\" b = '2'", "b"), Some("2"));
+        assert_eq!(attribute("<svg data-width=\"1\"", "width"), None);
+    }
+}
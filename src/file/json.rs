@@ -0,0 +1,146 @@
+use super::line_numbers::Location;
+use crate::{Context, R};
+use serde_json::Value;
+
+pub enum Document {
+    /// `pretty` is the pretty-printed document when `--pretty` is given.
+    Valid { pretty: Option<String> },
+    /// Where parsing failed.
+    Invalid(Location),
+}
+
+/// Writes a section validating and summarizing a json document.
+pub fn output(context: &mut Context, contents: &str) -> R<Document> {
+    match serde_json::from_str::<Value>(contents) {
+        Err(error) => {
+            writeln!(context.stdout, "invalid json: {}", error)?;
+            Ok(Document::Invalid(Location {
+                line: error.line(),
+                column: error.column(),
+            }))
+        }
+        Ok(value) => {
+            writeln!(context.stdout, "json: {}", summary(&value))?;
+            Ok(Document::Valid {
+                pretty: if context.args.pretty {
+                    Some(format!("{}\n", serde_json::to_string_pretty(&value)?))
+                } else {
+                    None
+                },
+            })
+        }
+    }
+}
+
+fn summary(value: &Value) -> String {
+    let description = match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(array) => match array.len() {
+            1 => "array with 1 element".to_string(),
+            n => format!("array with {} elements", n),
+        },
+        Value::Object(object) => match object.len() {
+            1 => "object with 1 key".to_string(),
+            n => format!("object with {} keys", n),
+        },
+    };
+    format!("{}, max depth {}", description, depth(value))
+}
+
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Array(array) => 1 + array.iter().map(depth).max().unwrap_or(0),
+        Value::Object(object) => 1 + object.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn summarizes_valid_documents() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.json"),
+            r#"{"a": [1, {"b": null}], "c": true}"#,
+        )?;
+        setup.run(vec!["foo.json"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "json: object with 2 keys, max depth 3\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_parse_errors_with_line_and_column() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.json"),
+            "{\n  \"a\": 1\n  \"b\": 2\n}\n",
+        )?;
+        setup.run(vec!["foo.json"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "invalid json: expected `,` or `}` at line 3 column 3\n"
+        );
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | {\n2 |   \"a\": 1\n3 !   \"b\": 2\n      ^\n4 | }\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn points_at_errors_in_a_last_line_without_newline() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.json"), "[1,]")?;
+        setup.run(vec!["foo.json"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 ! [1,]\n       ^\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_prints_minified_documents() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.json"), r#"{"b":[1,2],"a":{}}"#)?;
+        setup.run(vec!["--pretty", "foo.json"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | {\n2 |   \"b\": [\n3 |     1,\n4 |     2\n5 |   ],\n6 |   \"a\": {}\n7 | }\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_pretty_print_by_default() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.json"), r#"{"a":1}"#)?;
+        setup.run(vec!["foo.json"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | {\"a\":1}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn summarizes_scalars_and_arrays() {
+        assert_eq!(summary(&Value::Null), "null, max depth 0");
+        assert_eq!(
+            summary(&serde_json::json!([[1], 2])),
+            "array with 2 elements, max depth 2"
+        );
+    }
+}
@@ -1,37 +1,125 @@
-use crate::R;
+use crate::git;
+use colored::*;
 use source::Source;
 use std::path::Path;
 
-pub fn add(file: &Path, mut input: Source<char>) -> R<Source<String>> {
-    let max_number_length = max_number_length(file)?;
+/// A 1-based position in the contents, e.g. of a parse error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Adds line numbers to `input`, which is the (colorized) version of
+/// `contents`. Git annotations are only added when `file` is given, i.e.
+/// when `contents` are the unmodified contents of that file. The line of
+/// `error` gets marked in the gutter, with a caret below it pointing at the
+/// column.
+pub fn add(
+    contents: &str,
+    file: Option<&Path>,
+    blame: bool,
+    error: Option<Location>,
+    mut input: Source<char>,
+) -> Source<String> {
+    let max_number_length = max_number_length(contents);
+    let changes = file.and_then(git::line_changes).unwrap_or_default();
+    let blame = if blame {
+        file.and_then(git::blame)
+    } else {
+        None
+    };
+    let author_width = blame
+        .iter()
+        .flatten()
+        .map(|line| line.author.chars().count())
+        .max()
+        .unwrap_or(0);
+    let blame_width = if blame.is_some() { 9 + author_width } else { 0 };
+    let caret = move |line_number: i32| match error {
+        Some(error) if error.line == line_number as usize => format!(
+            "{}   {}{}\n",
+            " ".repeat(blame_width + max_number_length),
+            " ".repeat(error.column.max(1) - 1),
+            "^".red().bold()
+        ),
+        _ => "".to_string(),
+    };
     let mut line_start = true;
     let mut line_number = 0;
-    Ok(Source::new(move || match input.next() {
+    let mut finished = false;
+    Source::new(move || match input.next() {
         Some(char) if line_start => {
             line_number += 1;
+            let separator = if error.map(|error| error.line) == Some(line_number as usize) {
+                "!".red().bold().to_string()
+            } else {
+                match changes.get(&(line_number as usize)) {
+                    Some(change) => change.marker(),
+                    None => "|".to_string(),
+                }
+            };
+            let blame = match &blame {
+                Some(blame) => render_blame(blame, author_width, line_number as usize),
+                None => "".to_string(),
+            };
             Some(if char == '\n' {
-                format!("{} |\n", pad(max_number_length, line_number))
+                format!(
+                    "{}{} {}\n{}",
+                    blame,
+                    pad(max_number_length, line_number),
+                    separator,
+                    caret(line_number)
+                )
             } else {
                 line_start = false;
-                format!("{} | {}", pad(max_number_length, line_number), char)
+                format!(
+                    "{}{} {} {}",
+                    blame,
+                    pad(max_number_length, line_number),
+                    separator,
+                    char
+                )
             })
         }
         Some('\n') => {
             line_start = true;
-            Some("\n".to_string())
+            Some(format!("\n{}", caret(line_number)))
         }
         Some(char) => Some(char.to_string()),
+        None if !line_start && !finished && !caret(line_number).is_empty() => {
+            finished = true;
+            Some(format!("\n{}", caret(line_number)))
+        }
         None => None,
-    }))
+    })
+}
+
+/// Renders the commit and author of a line. Consecutive lines from the
+/// same commit are grouped by leaving the column blank after the first one.
+fn render_blame(blame: &[git::BlameLine], author_width: usize, line_number: usize) -> String {
+    let same_as_previous = line_number >= 2
+        && match (blame.get(line_number - 2), blame.get(line_number - 1)) {
+            (Some(previous), Some(current)) => previous.commit == current.commit,
+            _ => false,
+        };
+    match blame.get(line_number - 1) {
+        Some(line) if !same_as_previous => format!(
+            "{} {:width$} ",
+            line.commit.yellow(),
+            line.author,
+            width = author_width
+        ),
+        _ => format!("{:width$} ", "", width = 8 + author_width),
+    }
 }
 
-fn max_number_length(file: &Path) -> R<usize> {
-    let count = Source::read_utf8_file(file)?.count(|char| char == '\n');
-    let max_digits = count.to_string().len();
-    Ok(max_digits)
+pub fn max_number_length(contents: &str) -> usize {
+    let count = contents.matches('\n').count();
+    count.to_string().len()
 }
 
-fn pad(max_number_length: usize, n: i32) -> String {
+pub fn pad(max_number_length: usize, n: i32) -> String {
     let number_string = n.to_string();
     let padding = if number_string.len() < max_number_length {
         max_number_length - number_string.len()
@@ -46,6 +134,7 @@ fn pad(max_number_length: usize, n: i32) -> String {
 mod test {
     use super::*;
     use crate::test::*;
+    use crate::R;
     use source::source;
     use std::fs;
 
@@ -78,6 +167,119 @@ mod test {
             .collect::<Vec<String>>())
     }
 
+    mod git_changes {
+        use super::*;
+
+        #[test]
+        fn marks_changed_lines_in_the_gutter() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nb\nc\nd\n")?;
+            setup.git(&["add", "foo"])?;
+            setup.git(&["commit", "-q", "-m", "initial"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nB\nc\nnew\n")?;
+            setup.run(vec!["foo"])?;
+            assert_eq!(
+                setup.get_section(1),
+                format!(
+                    "1 | a\n2 {} B\n3 | c\n4 {} new\n",
+                    "~".yellow(),
+                    "~".yellow()
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn marks_added_and_deleted_lines() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nb\nc\n")?;
+            setup.git(&["add", "foo"])?;
+            setup.git(&["commit", "-q", "-m", "initial"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nc\nd\n\n")?;
+            setup.run(vec!["foo"])?;
+            assert_eq!(
+                setup.get_section(1),
+                format!(
+                    "1 {} a\n2 | c\n3 {} d\n4 {}\n",
+                    "-".red(),
+                    "+".green(),
+                    "+".green()
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn treats_file_names_literally() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("a[1].txt"), "a\n")?;
+            fs::write(setup.tempdir().join("a1.txt"), "a\n")?;
+            setup.git(&["add", "."])?;
+            setup.git(&["commit", "-q", "-m", "initial"])?;
+            fs::write(setup.tempdir().join("a1.txt"), "b\n")?;
+            setup.run(vec!["a[1].txt"])?;
+            assert_eq!(setup.get_section(1), "1 | a\n");
+            Ok(())
+        }
+
+        #[test]
+        fn does_not_mark_untracked_files() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "a\n")?;
+            setup.run(vec!["foo"])?;
+            assert_eq!(setup.get_section(1), "1 | a\n");
+            Ok(())
+        }
+    }
+
+    mod blame {
+        use super::*;
+
+        #[test]
+        fn shows_commit_and_author_grouped_by_commit() -> R<()> {
+            let mut setup = setup()?;
+            setup.git(&["init", "-q"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nb\n")?;
+            setup.git(&["add", "foo"])?;
+            setup.git(&["commit", "-q", "-m", "first"])?;
+            fs::write(setup.tempdir().join("foo"), "a\nb\nc\n")?;
+            setup.git(&[
+                "commit",
+                "-q",
+                "-a",
+                "-m",
+                "second",
+                "--author",
+                "Bob <b@example.com>",
+            ])?;
+            let first = setup.git(&["rev-parse", "--short=7", "HEAD~"])?;
+            let second = setup.git(&["rev-parse", "--short=7", "HEAD"])?;
+            setup.run(vec!["--blame", "foo"])?;
+            assert_eq!(
+                setup.get_section(1),
+                format!(
+                    "{} Alice 1 | a\n              2 | b\n{} Bob   3 | c\n",
+                    first.trim_end().yellow(),
+                    second.trim_end().yellow()
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn is_ignored_outside_of_repositories() -> R<()> {
+            let mut setup = setup()?;
+            fs::write(setup.tempdir().join("foo"), "a\n")?;
+            setup.run(vec!["--blame", "foo"])?;
+            assert_eq!(setup.get_section(1), "1 | a\n");
+            Ok(())
+        }
+    }
+
     mod padding {
         use super::*;
 
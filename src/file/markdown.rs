@@ -0,0 +1,347 @@
+use super::colorize::{colorize_language, Language};
+use super::escapes::sanitize;
+use crate::args::Args;
+use colored::*;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::borrow::Cow;
+
+const RULE_WIDTH: usize = 20;
+
+/// Renders markdown for the terminal: styles inline markup, highlights
+/// fenced code blocks and indents lists and block quotes.
+pub fn render(args: &Args, contents: &str) -> String {
+    let mut renderer = Renderer {
+        line_start: true,
+        ..Renderer::default()
+    };
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(contents, options) {
+        renderer.event(sanitize_event(args, event));
+    }
+    format!("{}\n", renderer.output.trim_end())
+}
+
+/// Neutralizes the text of events that end up in the output. Entities like
+/// `&#27;` are only decoded by the parser, so this can't be done up front.
+fn sanitize_event<'a>(args: &Args, event: Event<'a>) -> Event<'a> {
+    let clean = |text: CowStr<'a>| {
+        let clean = match sanitize(args, &text) {
+            Cow::Owned(clean) => Some(clean),
+            Cow::Borrowed(_) => None,
+        };
+        clean.map_or(text, CowStr::from)
+    };
+    match event {
+        Event::Text(text) => Event::Text(clean(text)),
+        Event::Code(code) => Event::Code(clean(code)),
+        Event::Html(html) => Event::Html(clean(html)),
+        Event::InlineHtml(html) => Event::InlineHtml(clean(html)),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: clean(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: clean(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    }
+}
+
+#[derive(Default)]
+struct Renderer {
+    output: String,
+    line_start: bool,
+    pending_blank_line: bool,
+    prefixes: Vec<String>,
+    lists: Vec<Option<u64>>,
+    heading: Option<HeadingLevel>,
+    strong: usize,
+    emphasis: usize,
+    strikethrough: usize,
+    links: Vec<String>,
+    code_block: Option<(Language, String)>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match &mut self.code_block {
+                Some((_, code)) => code.push_str(&text),
+                None => {
+                    let styled = self.style(&text);
+                    self.write(&styled);
+                }
+            },
+            Event::Code(code) => self.write(&code.green().to_string()),
+            Event::Html(html) | Event::InlineHtml(html) => self.write(&html.dimmed().to_string()),
+            Event::SoftBreak | Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.write(&"─".repeat(RULE_WIDTH).dimmed().to_string());
+                self.blank_line();
+            }
+            Event::TaskListMarker(checked) => self.write(if checked { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        self.flush_blank_line();
+        match tag {
+            Tag::Heading { level, .. } => self.heading = Some(level),
+            Tag::BlockQuote(_) => self.prefixes.push(format!("{} ", "│".dimmed())),
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        Language::from_name(info.split_whitespace().next().unwrap_or(""))
+                    }
+                    CodeBlockKind::Indented => Language::Plain,
+                };
+                self.code_block = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                if !self.line_start {
+                    self.newline();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                if !self.line_start {
+                    self.newline();
+                }
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.write(&bullet.cyan().bold().to_string());
+                self.prefixes.push(" ".repeat(bullet.chars().count()));
+            }
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strong => self.strong += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            Tag::Link { dest_url, .. } => self.links.push(dest_url.to_string()),
+            Tag::Image { dest_url, .. } => {
+                self.write("[image: ");
+                self.links.push(dest_url.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.blank_line(),
+            TagEnd::Heading(_) => {
+                self.heading = None;
+                self.blank_line();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.prefixes.pop();
+                self.blank_line();
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, code)) = self.code_block.take() {
+                    self.prefixes.push("    ".to_string());
+                    for line in colorize_language(&code, language).join("").lines() {
+                        self.write(line);
+                        self.newline();
+                    }
+                    self.prefixes.pop();
+                    self.blank_line();
+                }
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            TagEnd::Item => {
+                self.prefixes.pop();
+                if !self.line_start {
+                    self.newline();
+                }
+            }
+            TagEnd::Emphasis => self.emphasis -= 1,
+            TagEnd::Strong => self.strong -= 1,
+            TagEnd::Strikethrough => self.strikethrough -= 1,
+            TagEnd::Link => {
+                if let Some(target) = self.links.pop() {
+                    self.write(&format!(" ({})", target).dimmed().to_string());
+                }
+            }
+            TagEnd::Image => {
+                if let Some(target) = self.links.pop() {
+                    self.write(&format!(" ({})]", target).dimmed().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn style(&self, text: &str) -> String {
+        let mut styled = text.normal();
+        match self.heading {
+            Some(HeadingLevel::H1) => styled = styled.magenta().bold().underline(),
+            Some(_) => styled = styled.blue().bold(),
+            None => {}
+        }
+        if self.strong > 0 {
+            styled = styled.bold();
+        }
+        if self.emphasis > 0 {
+            styled = styled.italic();
+        }
+        if self.strikethrough > 0 {
+            styled = styled.strikethrough();
+        }
+        if !self.links.is_empty() {
+            styled = styled.blue().underline();
+        }
+        styled.to_string()
+    }
+
+    fn write(&mut self, text: &str) {
+        self.flush_blank_line();
+        if self.line_start {
+            self.output.push_str(&self.prefixes.concat());
+            self.line_start = false;
+        }
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        self.line_start = true;
+    }
+
+    /// Ends the current block. The separating empty line is only written
+    /// once more text follows, so that block quotes and lists don't end in
+    /// trailing empty lines.
+    fn blank_line(&mut self) {
+        if !self.line_start {
+            self.newline();
+        }
+        self.pending_blank_line = true;
+    }
+
+    fn flush_blank_line(&mut self) {
+        if self.pending_blank_line {
+            self.pending_blank_line = false;
+            if !self.output.is_empty() {
+                self.output
+                    .push_str(self.prefixes.concat().trim_end_matches(' '));
+                self.output.push('\n');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    fn render_default(markdown: &str) -> String {
+        let args = Args::parse(std::iter::empty()).unwrap();
+        render(&args, markdown)
+    }
+
+    fn render_plain(markdown: &str) -> R<String> {
+        Ok(String::from_utf8(strip(render_default(markdown))?)?)
+    }
+
+    #[test]
+    fn styles_headings_and_inline_markup() {
+        assert_eq!(
+            render_default("# Title\n\nsome *emphasis*, **strong** and `code`\n"),
+            format!(
+                "{}\n\nsome {}, {} and {}\n",
+                "Title".magenta().bold().underline(),
+                "emphasis".italic(),
+                "strong".bold(),
+                "code".green()
+            )
+        );
+    }
+
+    #[test]
+    fn shows_link_targets() -> R<()> {
+        assert_eq!(
+            render_plain("see [the docs](https://example.com)\n")?,
+            "see the docs (https://example.com)\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn indents_lists_and_block_quotes() -> R<()> {
+        assert_eq!(
+            render_plain("- foo\n  - bar\n- baz\n\n1. one\n2. two\n\n> quoted\n> text\n\nafter\n")?,
+            "• foo\n  • bar\n• baz\n\n1. one\n2. two\n\n│ quoted\n│ text\n\nafter\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn highlights_fenced_code_blocks_by_language() {
+        assert_eq!(
+            render_default("```toml\nname = \"si\"\n```\n"),
+            format!(
+                "    {} = {}\n",
+                "name".blue().bold(),
+                "\"si\"".yellow().bold()
+            )
+        );
+    }
+
+    #[test]
+    fn neutralizes_decoded_entities() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("README.md"),
+            "&#27;[2J [a](&#27;]0;b) `\u{1b}[1m`\n",
+        )?;
+        setup.run(vec!["--render", "README.md"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert!(!setup.stdout().contains("\u{1b}]0;b"));
+        assert!(!setup.stdout().contains("\u{1b}[1m"));
+        assert!(String::from_utf8(strip(setup.stdout())?)?.contains("^[[2J a (^[]0;b) ^[[1m"));
+        Ok(())
+    }
+
+    #[test]
+    fn is_only_used_with_render() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("README.md"), "# foo\n")?;
+        setup.run(vec!["README.md"])?;
+        assert_eq!(setup.get_section(1), "1 | # foo\n");
+        setup.run(vec!["--render", "README.md"])?;
+        assert!(setup
+            .stdout()
+            .ends_with(&format!("{}\n", "foo".magenta().bold().underline())));
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use super::escapes::sanitize;
+use crate::args::Args;
+use crate::utils::pluralize;
+use crate::{Context, R};
+use colored::*;
+use std::borrow::Cow;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const MIN_COLUMN_WIDTH: usize = 3;
+const COLUMN_SEPARATOR: &str = " │ ";
+
+pub fn read(contents: &str, delimiter: u8) -> R<Vec<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let mut rows = vec![];
+    for record in reader.records() {
+        rows.push(record?.iter().map(|cell| cell.to_string()).collect());
+    }
+    Ok(rows)
+}
+
+/// Writes row and column counts and the inferred type of every column.
+/// The first row is treated as the header.
+pub fn output_stats(context: &mut Context, rows: &[Vec<String>]) -> R<()> {
+    let columns = column_count(rows);
+    writeln!(
+        context.stdout,
+        "{}, {}",
+        pluralize(rows.len().saturating_sub(1), "row", "rows"),
+        pluralize(columns, "column", "columns")
+    )?;
+    for column in 0..columns {
+        let name = rows
+            .first()
+            .and_then(|header| header.get(column))
+            .map_or("", |name| name.as_str());
+        let cells = rows
+            .iter()
+            .skip(1)
+            .filter_map(|row| row.get(column))
+            .map(|cell| cell.as_str());
+        writeln!(
+            context.stdout,
+            "  {}: {}",
+            render_text(&context.args, name),
+            infer_type(cells)
+        )?;
+    }
+    Ok(())
+}
+
+fn infer_type<'a, I: Iterator<Item = &'a str>>(cells: I) -> &'static str {
+    let cells: Vec<&str> = cells
+        .map(|cell| cell.trim())
+        .filter(|cell| !cell.is_empty())
+        .collect();
+    if cells.is_empty() {
+        "empty"
+    } else if cells.iter().all(|cell| cell.parse::<i64>().is_ok()) {
+        "integer"
+    } else if cells.iter().all(|cell| cell.parse::<f64>().is_ok()) {
+        "float"
+    } else if cells
+        .iter()
+        .all(|cell| cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false"))
+    {
+        "boolean"
+    } else {
+        "text"
+    }
+}
+
+pub fn output_table(context: &mut Context, rows: &[Vec<String>]) -> R<()> {
+    let rows: Vec<Vec<Cow<str>>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| render_text(&context.args, cell))
+                .collect()
+        })
+        .collect();
+    let columns = column_count(&rows);
+    let mut widths = vec![0; columns];
+    for row in &rows {
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(cell.width());
+        }
+    }
+    if let Some(terminal_width) = context.terminal_width {
+        fit_widths(&mut widths, terminal_width);
+    }
+    for (index, row) in rows.iter().enumerate() {
+        let cells = widths
+            .iter()
+            .enumerate()
+            .map(|(column, width)| {
+                let cell = render_cell(row.get(column).map_or("", |x| x.as_ref()), *width);
+                if index == 0 {
+                    cell.bold().to_string()
+                } else {
+                    cell
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(
+            context.stdout,
+            "{}",
+            cells.join(COLUMN_SEPARATOR).trim_end()
+        )?;
+        if index == 0 {
+            let rule = widths
+                .iter()
+                .map(|width| "─".repeat(*width))
+                .collect::<Vec<_>>();
+            writeln!(context.stdout, "{}", rule.join("─┼─"))?;
+        }
+    }
+    Ok(())
+}
+
+fn column_count<T>(rows: &[Vec<T>]) -> usize {
+    rows.iter().map(|row| row.len()).max().unwrap_or(0)
+}
+
+/// Shrinks the widest columns until the table fits into the terminal.
+fn fit_widths(widths: &mut [usize], terminal_width: usize) {
+    let separators = widths.len().saturating_sub(1) * COLUMN_SEPARATOR.chars().count();
+    while widths.iter().sum::<usize>() + separators > terminal_width {
+        match widths.iter_mut().max() {
+            Some(widest) if *widest > MIN_COLUMN_WIDTH => *widest -= 1,
+            _ => break,
+        }
+    }
+}
+
+/// Neutralizes escape sequences and shows the line breaks and tabs of
+/// quoted cells as markers, so they don't break up the table.
+fn render_text<'a>(args: &Args, text: &'a str) -> Cow<'a, str> {
+    let text = sanitize(args, text);
+    if text.contains(['\n', '\r', '\t']) {
+        Cow::Owned(
+            text.replace("\r\n", "⏎")
+                .replace(['\n', '\r'], "⏎")
+                .replace('\t', "→"),
+        )
+    } else {
+        text
+    }
+}
+
+/// Pads or truncates a cell to a display width. Wide characters take up
+/// two columns and combining characters none.
+fn render_cell(cell: &str, width: usize) -> String {
+    let cell_width = cell.width();
+    if cell_width > width {
+        let available = width.saturating_sub(1);
+        let mut truncated = String::new();
+        let mut truncated_width = 0;
+        for char in cell.chars() {
+            let char_width = char.width().unwrap_or(0);
+            if truncated_width + char_width > available {
+                break;
+            }
+            truncated.push(char);
+            truncated_width += char_width;
+        }
+        format!("{}…{}", truncated, " ".repeat(available - truncated_width))
+    } else {
+        format!("{}{}", cell, " ".repeat(width - cell_width))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use std::fs;
+    use strip_ansi_escapes::strip;
+
+    #[test]
+    fn renders_csv_files_as_aligned_tables() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.csv"),
+            "name,age\nalice,42\n\"bob, jr.\",7\n",
+        )?;
+        setup.run(vec!["foo.csv"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "name     │ age\n─────────┼────\nalice    │ 42\nbob, jr. │ 7\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_stats_with_inferred_column_types() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.tsv"),
+            "name\tage\tscore\tactive\tnotes\nalice\t42\t1.5\ttrue\t\nbob\t7\t2\tFALSE\t\n",
+        )?;
+        setup.run(vec!["foo.tsv"])?;
+        assert_eq!(
+            setup.get_section(1),
+            "2 rows, 5 columns\n  name: text\n  age: integer\n  score: float\n  active: boolean\n  notes: empty\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncates_cells_to_fit_the_terminal_width() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.csv"),
+            format!("a,b\n{},{}\n", "x".repeat(40), "y".repeat(20)),
+        )?;
+        setup.run(vec!["foo.csv"])?;
+        let table = String::from_utf8(strip(setup.get_section(2))?)?;
+        assert_eq!(
+            get_line(table.clone(), 2),
+            format!("{}… │ {}", "x".repeat(26), "y".repeat(20))
+        );
+        assert!(table
+            .lines()
+            .all(|line| line.chars().count() <= TEST_TERMINAL_WIDTH.unwrap()));
+        Ok(())
+    }
+
+    #[test]
+    fn neutralizes_escape_sequences_in_cells() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.csv"),
+            "\u{1b}[2J,b\n1,\u{1b}[1m\n",
+        )?;
+        setup.run(vec!["foo.csv"])?;
+        assert!(!setup.stdout().contains("\u{1b}[2J"));
+        assert!(!setup.stdout().contains("\u{1b}[1m"));
+        assert_eq!(get_line(setup.get_section(1), 1), "  ^[[2J: integer");
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "^[[2J │ b\n──────┼──────\n1     │ ^[[1m\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_line_breaks_and_tabs_in_cells_as_markers() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.csv"), "a,b\n\"x\ny\",\"1\t2\"\n")?;
+        setup.run(vec!["foo.csv"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "a   │ b\n────┼────\nx⏎y │ 1→2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aligns_wide_and_combining_characters() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(
+            setup.tempdir().join("foo.csv"),
+            "name,x\n日本,1\ne\u{301}e\u{301},2\n",
+        )?;
+        setup.run(vec!["foo.csv"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "name │ x\n─────┼──\n日本 │ 1\ne\u{301}e\u{301}   │ 2\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncates_wide_characters_by_display_width() {
+        assert_eq!(render_cell("日本語", 4), "日… ");
+        assert_eq!(render_cell("日本語", 6), "日本語");
+    }
+
+    #[test]
+    fn shows_the_numbered_contents_with_raw() -> R<()> {
+        let mut setup = setup()?;
+        fs::write(setup.tempdir().join("foo.csv"), "a,b\n1,2\n")?;
+        setup.run(vec!["--raw", "foo.csv"])?;
+        assert_eq!(
+            String::from_utf8(strip(setup.get_section(2))?)?,
+            "1 | a,b\n2 | 1,2\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+/// Replaces invisible characters with visible markers: tabs, trailing
+/// spaces, non-breaking spaces, carriage returns and other control codes.
+/// Zero-width and other formatting characters are shown as code points.
+/// Newlines are kept, so line numbers still match the original contents.
+pub fn visualize(contents: &str) -> String {
+    let mut result = String::new();
+    for line in contents.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        let (line, carriage_return) = match line.strip_suffix('\r') {
+            Some(line) => (line, "\r"),
+            None => (line, ""),
+        };
+        let content = line.trim_end_matches(' ');
+        for char in content.chars() {
+            push_visible(&mut result, char);
+        }
+        for _ in content.len()..line.len() {
+            result.push('·');
+        }
+        if !carriage_return.is_empty() {
+            push_visible(&mut result, '\r');
+        }
+        result.push_str(newline);
+    }
+    result
+}
+
+fn push_visible(result: &mut String, char: char) {
+    match char {
+        '\t' => result.push('→'),
+        '\u{a0}' => result.push('⍽'),
+        '\u{0}'..='\u{1f}' => {
+            result.push(std::char::from_u32(0x2400 + char as u32).unwrap_or(char))
+        }
+        '\u{7f}' => result.push('␡'),
+        char if is_invisible(char) => result.push_str(&format!("<U+{:04X}>", char as u32)),
+        char => result.push(char),
+    }
+}
+
+/// C1 control codes and zero-width or other formatting characters.
+fn is_invisible(char: char) -> bool {
+    matches!(
+        char,
+        '\u{80}'..='\u{9f}'
+            | '\u{ad}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{2028}'..='\u{202e}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{feff}'
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use crate::R;
+    use std::fs;
+
+    #[test]
+    fn marks_tabs_and_trailing_spaces() {
+        assert_eq!(visualize("\tfoo bar  \n"), "→foo bar··\n");
+        assert_eq!(visualize("foo \nbar "), "foo·\nbar·");
+    }
+
+    #[test]
+    fn marks_special_spaces_and_control_codes() {
+        assert_eq!(visualize("a\u{a0}b\r\n"), "a⍽b␍\n");
+        assert_eq!(visualize("a\u{200b}b\u{feff}"), "a<U+200B>b<U+FEFF>");
+        assert_eq!(visualize("\u{0}\u{7}\u{1b}\u{7f}\u{85}"), "␀␇␛␡<U+0085>");
+    }
+
+    #[test]
+    fn is_enabled_with_a_flag() -> R<()> {
+        let mut setup = setup()?;
+        fs::write("foo", "a\tb \r\n")?;
+        setup.run(vec!["--show-whitespace", "foo"])?;
+        assert_eq!(setup.get_section(1), "1 | a→b·␍\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+use colored::*;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs `git` in the given directory. Returns `None` when git is not
+/// installed, the directory is not inside a repository or the command
+/// fails for any other reason.
+///
+/// The repository may not be trusted, so its config must not be able to
+/// make git run commands.
+pub fn git(directory: &Path, args: &[&str]) -> Option<String> {
+    let output = git_bytes(directory, args)?;
+    Some(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Like `git`, for output containing paths, which don't have to be valid
+/// UTF-8.
+fn git_bytes(directory: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args([
+            "-c",
+            "core.fsmonitor=false",
+            "-c",
+            "core.hooksPath=/dev/null",
+            "-c",
+            "diff.external=",
+        ])
+        // File names are passed as pathspecs, which would treat `*`, `?`
+        // and `[` as globs otherwise.
+        .arg("--literal-pathspecs")
+        .args(args)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(output.stdout)
+    } else {
+        None
+    }
+}
+
+pub fn work_tree_root(directory: &Path) -> Option<PathBuf> {
+    let output = git_bytes(directory, &["rev-parse", "--show-toplevel"])?;
+    let root = output.strip_suffix(b"\n").unwrap_or(&output);
+    Some(PathBuf::from(OsStr::from_bytes(root)))
+}
+
+/// Ordered by precedence: when a directory contains entries with different
+/// statuses, it shows the greatest one.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Status {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl Status {
+    pub fn marker(self) -> String {
+        match self {
+            Status::Ignored => "!".dimmed(),
+            Status::Untracked => "?".red(),
+            Status::Staged => "S".green(),
+            Status::Modified => "M".yellow(),
+            Status::Conflicted => "U".magenta().bold(),
+        }
+        .to_string()
+    }
+}
+
+pub struct Statuses {
+    entries: HashMap<PathBuf, Status>,
+    directories: HashMap<PathBuf, Status>,
+    /// Number of entries that aren't ignored.
+    dirty_files: usize,
+}
+
+impl Statuses {
+    pub fn read(directory: &Path) -> Option<Statuses> {
+        let root = work_tree_root(directory)?;
+        let output = git_bytes(directory, &["status", "--porcelain=v1", "-z", "--ignored"])?;
+        let mut statuses = Statuses {
+            entries: HashMap::new(),
+            directories: HashMap::new(),
+            dirty_files: 0,
+        };
+        let mut fields = output.split(|byte| *byte == b'\0');
+        while let Some(field) = fields.next() {
+            if field.len() < 4 {
+                continue;
+            }
+            let code = match std::str::from_utf8(&field[..2]) {
+                Ok(code) => code,
+                Err(_) => continue,
+            };
+            let path = &field[3..];
+            let path = root.join(OsStr::from_bytes(path.strip_suffix(b"/").unwrap_or(path)));
+            if code.starts_with('R') || code.starts_with('C') {
+                fields.next();
+            }
+            if code != "!!" {
+                statuses.dirty_files += 1;
+            }
+            if let Some(status) = parse_code(code) {
+                statuses.insert(&root, path, status);
+            }
+        }
+        Some(statuses)
+    }
+
+    fn insert(&mut self, root: &Path, path: PathBuf, status: Status) {
+        if status != Status::Ignored {
+            for ancestor in path.ancestors().skip(1) {
+                let entry = self
+                    .directories
+                    .entry(ancestor.to_path_buf())
+                    .or_insert(status);
+                *entry = (*entry).max(status);
+                if ancestor == root {
+                    break;
+                }
+            }
+        }
+        self.entries.insert(path, status);
+    }
+
+    /// Looks up the status of a file or directory. Entries inside untracked
+    /// or ignored directories inherit their status.
+    pub fn get(&self, path: &Path) -> Option<Status> {
+        let path = absolute(path)?;
+        if let Some(status) = self.entries.get(&path) {
+            return Some(*status);
+        }
+        if let Some(status) = self.directories.get(&path) {
+            return Some(*status);
+        }
+        path.ancestors()
+            .skip(1)
+            .find_map(|ancestor| self.entries.get(ancestor))
+            .copied()
+    }
+}
+
+pub struct Summary {
+    branch: Option<String>,
+    head: Option<String>,
+    upstream: Option<Upstream>,
+    dirty_files: usize,
+}
+
+struct Upstream {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+impl Summary {
+    /// Takes the dirty files from `statuses`, which have been read for the
+    /// same directory, to avoid running `git status` twice.
+    pub fn read(directory: &Path, statuses: &Statuses) -> Summary {
+        let branch = git(directory, &["symbolic-ref", "--short", "-q", "HEAD"])
+            .map(|output| output.trim_end().to_string());
+        let head = git(directory, &["log", "-1", "--format=%h %s"])
+            .map(|output| output.trim_end().to_string())
+            .filter(|head| !head.is_empty());
+        let upstream = git(
+            directory,
+            &[
+                "rev-parse",
+                "--abbrev-ref",
+                "--symbolic-full-name",
+                "@{upstream}",
+            ],
+        )
+        .and_then(|name| {
+            let counts = git(
+                directory,
+                &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+            )?;
+            let mut counts = counts.split_whitespace().map(|x| x.parse().ok());
+            Some(Upstream {
+                name: name.trim_end().to_string(),
+                ahead: counts.next()??,
+                behind: counts.next()??,
+            })
+        });
+        Summary {
+            branch,
+            head,
+            upstream,
+            dirty_files: statuses.dirty_files,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            formatter,
+            "branch: {}",
+            self.branch.as_deref().unwrap_or("(detached)")
+        )?;
+        if let Some(upstream) = &self.upstream {
+            write!(
+                formatter,
+                " (ahead {}, behind {} of {})",
+                upstream.ahead, upstream.behind, upstream.name
+            )?;
+        }
+        write!(
+            formatter,
+            "\nHEAD: {}",
+            self.head.as_deref().unwrap_or("(no commits)")
+        )?;
+        write!(formatter, "\ndirty files: {}", self.dirty_files)?;
+        Ok(())
+    }
+}
+
+const HISTORY_LENGTH: usize = 5;
+
+pub struct History {
+    recent: Vec<String>,
+    first: Option<String>,
+}
+
+impl History {
+    /// Reads the commits that touched a file, following renames. Returns
+    /// `None` outside of repositories.
+    pub fn read(file: &Path) -> Option<History> {
+        let (directory, name) = split_path(file)?;
+        work_tree_root(&directory)?;
+        let log = git(
+            &directory,
+            &[
+                "log",
+                "--follow",
+                "--no-ext-diff",
+                "--no-textconv",
+                "--date=short",
+                "--format=%h %ad %an: %s",
+                "--",
+                &name,
+            ],
+        )
+        .unwrap_or_default();
+        let commits: Vec<&str> = log.lines().collect();
+        Some(History {
+            recent: commits
+                .iter()
+                .take(HISTORY_LENGTH)
+                .map(|x| x.to_string())
+                .collect(),
+            first: commits.last().map(|x| x.to_string()),
+        })
+    }
+}
+
+impl Display for History {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match &self.first {
+            None => write!(formatter, "no commits touching this file")?,
+            Some(first) => {
+                writeln!(formatter, "recent commits:")?;
+                for commit in &self.recent {
+                    writeln!(formatter, "{}", commit)?;
+                }
+                write!(formatter, "first commit:\n{}", first)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+}
+
+/// Reads `git blame` for a file, one entry per line.
+pub fn blame(file: &Path) -> Option<Vec<BlameLine>> {
+    let (directory, name) = split_path(file)?;
+    let output = git(
+        &directory,
+        &["blame", "--porcelain", "--no-textconv", "--", &name],
+    )?;
+    Some(parse_blame(&output))
+}
+
+fn parse_blame(output: &str) -> Vec<BlameLine> {
+    let mut result = vec![];
+    let mut authors: HashMap<&str, &str> = HashMap::new();
+    let mut current = "";
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            result.push(BlameLine {
+                commit: current.chars().take(7).collect(),
+                author: authors.get(current).unwrap_or(&"").to_string(),
+            });
+        } else if let Some(author) = line.strip_prefix("author ") {
+            authors.insert(current, author);
+        } else if let Some(hash) = line.split_whitespace().next() {
+            // Sha-1 or sha-256, depending on the repository.
+            if hash.len() >= 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                current = hash;
+            }
+        }
+    }
+    result
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted right after this line (or before it, for deletions
+    /// at the start of a file).
+    Deleted,
+}
+
+impl LineChange {
+    pub fn marker(self) -> String {
+        match self {
+            LineChange::Added => "+".green(),
+            LineChange::Modified => "~".yellow(),
+            LineChange::Deleted => "-".red(),
+        }
+        .to_string()
+    }
+}
+
+/// Changes of the working tree version of a file relative to `HEAD`, keyed
+/// by 1-based line numbers.
+pub fn line_changes(file: &Path) -> Option<HashMap<usize, LineChange>> {
+    let (directory, name) = split_path(file)?;
+    let diff = git(
+        &directory,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-textconv",
+            "-U0",
+            "HEAD",
+            "--",
+            &name,
+        ],
+    )?;
+    Some(parse_line_changes(&diff))
+}
+
+fn parse_line_changes(diff: &str) -> HashMap<usize, LineChange> {
+    let mut result = HashMap::new();
+    for line in diff.lines() {
+        let hunk = match line
+            .strip_prefix("@@ -")
+            .and_then(|x| x.split(" @@").next())
+        {
+            Some(hunk) => hunk,
+            None => continue,
+        };
+        let mut ranges = hunk.split(" +");
+        let (old, new) = match (ranges.next(), ranges.next()) {
+            (Some(old), Some(new)) => (parse_range(old), parse_range(new)),
+            _ => continue,
+        };
+        let ((_, old_count), (new_start, new_count)) = match (old, new) {
+            (Some(old), Some(new)) => (old, new),
+            _ => continue,
+        };
+        if new_count == 0 {
+            result.insert(new_start.max(1), LineChange::Deleted);
+        } else {
+            for offset in 0..new_count {
+                let change = if offset < old_count {
+                    LineChange::Modified
+                } else {
+                    LineChange::Added
+                };
+                result.insert(new_start + offset, change);
+            }
+        }
+    }
+    result
+}
+
+/// Parses ranges like `12,3` or `12` (which means a count of 1).
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let mut parts = range.split(',');
+    let start = parts.next()?.parse().ok()?;
+    let count = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+    Some((start, count))
+}
+
+fn split_path(file: &Path) -> Option<(PathBuf, String)> {
+    let directory = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = file.file_name()?.to_string_lossy().into_owned();
+    Some((directory, name))
+}
+
+fn parse_code(code: &str) -> Option<Status> {
+    let mut chars = code.chars();
+    let (index, work_tree) = (chars.next()?, chars.next()?);
+    Some(match (index, work_tree) {
+        ('!', '!') => Status::Ignored,
+        ('?', '?') => Status::Untracked,
+        ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => Status::Conflicted,
+        (_, 'M') | (_, 'D') | (_, 'T') => Status::Modified,
+        (' ', _) => return None,
+        _ => Status::Staged,
+    })
+}
+
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => return fs::canonicalize(path).ok(),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use crate::R;
+
+    #[test]
+    fn parses_porcelain_status_codes() {
+        assert_eq!(parse_code(" M"), Some(Status::Modified));
+        assert_eq!(parse_code("M "), Some(Status::Staged));
+        assert_eq!(parse_code("MM"), Some(Status::Modified));
+        assert_eq!(parse_code("A "), Some(Status::Staged));
+        assert_eq!(parse_code("R "), Some(Status::Staged));
+        assert_eq!(parse_code("UU"), Some(Status::Conflicted));
+        assert_eq!(parse_code("AA"), Some(Status::Conflicted));
+        assert_eq!(parse_code("??"), Some(Status::Untracked));
+        assert_eq!(parse_code("!!"), Some(Status::Ignored));
+    }
+
+    #[test]
+    fn parses_hunk_headers_into_line_changes() {
+        let diff = "@@ -1 +1 @@\n-a\n+b\n@@ -3,0 +4,2 @@\n+c\n+d\n@@ -7,2 +9,0 @@\n-e\n-f\n";
+        let changes = parse_line_changes(diff);
+        assert_eq!(changes.get(&1), Some(&LineChange::Modified));
+        assert_eq!(changes.get(&4), Some(&LineChange::Added));
+        assert_eq!(changes.get(&5), Some(&LineChange::Added));
+        assert_eq!(changes.get(&9), Some(&LineChange::Deleted));
+        assert_eq!(changes.len(), 4);
+    }
+
+    #[test]
+    fn parses_porcelain_blame_output() {
+        let hash_a = "a".repeat(40);
+        let hash_b = "b".repeat(40);
+        let output = format!(
+            "{a} 1 1 2\nauthor Alice\nauthor-mail <alice@example.com>\n\tfoo\n{a} 2 2\n\tbar\n{b} 3 3 1\nauthor Bob\n\tbaz\n",
+            a = hash_a,
+            b = hash_b
+        );
+        let lines: Vec<(String, String)> = parse_blame(&output)
+            .into_iter()
+            .map(|line| (line.commit, line.author))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("aaaaaaa".to_string(), "Alice".to_string()),
+                ("aaaaaaa".to_string(), "Alice".to_string()),
+                ("bbbbbbb".to_string(), "Bob".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_blame_output_with_sha256_hashes() {
+        let output = format!("{} 1 1 1\nauthor Alice\n\tfoo\n", "c".repeat(64));
+        let lines = parse_blame(&output);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].commit, "ccccccc");
+        assert_eq!(lines[0].author, "Alice");
+    }
+
+    #[test]
+    fn hunks_with_more_new_lines_are_partly_added() {
+        let changes = parse_line_changes("@@ -2 +2,2 @@\n");
+        assert_eq!(changes.get(&2), Some(&LineChange::Modified));
+        assert_eq!(changes.get(&3), Some(&LineChange::Added));
+    }
+
+    #[test]
+    fn does_not_run_commands_from_the_repository_config() -> R<()> {
+        let mut setup = setup()?;
+        setup.git(&["init", "-q"])?;
+        let marker = setup.tempdir().join("pwned");
+        setup.git(&[
+            "config",
+            "core.fsmonitor",
+            &format!("touch '{}'; false", marker.display()),
+        ])?;
+        fs::write("foo", "foo\n")?;
+        setup.run(vec!["."])?;
+        assert!(setup.stdout().contains("foo"));
+        assert!(!marker.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_run_commands_when_diffing_files() -> R<()> {
+        let mut setup = setup()?;
+        setup.git(&["init", "-q"])?;
+        fs::write("foo", "foo\n")?;
+        fs::write(".gitattributes", "foo diff=evil\n")?;
+        setup.git(&["add", "."])?;
+        setup.git(&["commit", "-q", "-m", "initial"])?;
+        let marker = setup.tempdir().join("pwned");
+        let command = format!("touch '{}'; false", marker.display());
+        setup.git(&["config", "core.fsmonitor", &command])?;
+        setup.git(&["config", "diff.evil.textconv", &command])?;
+        setup.git(&["config", "diff.external", &command])?;
+        fs::write("foo", "bar\n")?;
+        setup.run(vec!["foo"])?;
+        assert!(setup.stdout().contains("bar"));
+        assert!(!marker.exists());
+        Ok(())
+    }
+}
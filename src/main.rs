@@ -1,20 +1,25 @@
+mod archive;
+mod args;
+mod compression;
 mod directory;
 mod file;
+mod git;
 mod utils;
 
+use args::Args;
 use colored::*;
 use lexiclean::Lexiclean;
 use pager::Pager;
 use source::Source;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use utils::render_path;
 
 type R<A> = Result<A, Box<dyn std::error::Error>>;
 
 pub struct Context<'a> {
-    args: Vec<String>,
+    args: Args,
     stdout: &'a mut dyn Write,
     terminal_width: Option<usize>,
 }
@@ -24,10 +29,12 @@ fn wrap_main(action: fn(context: &mut Context) -> R<()>) {
     colored::control::set_override(true);
     Pager::with_pager("less -RFX").setup();
     let mut stdout = std::io::stdout();
-    let exitcode = match action(&mut Context {
-        args: std::env::args().collect(),
-        stdout: &mut stdout,
-        terminal_width,
+    let exitcode = match Args::parse(std::env::args().skip(1)).and_then(|args| {
+        action(&mut Context {
+            args,
+            stdout: &mut stdout,
+            terminal_width,
+        })
     }) {
         Ok(()) => 0,
         Err(error) => {
@@ -45,19 +52,43 @@ fn main() {
 }
 
 fn run(context: &mut Context) -> R<()> {
-    let entry = PathBuf::from(
-        context
-            .args
-            .get(1)
-            .cloned()
-            .unwrap_or_else(|| ".".to_string()),
-    );
-    show_information(context, entry)?;
+    let entry = context.args.path.clone();
+    match context.args.diff.clone() {
+        Some(other) => show_diff(context, entry, other)?,
+        None => show_information(context, entry)?,
+    }
+    Ok(())
+}
+
+fn show_diff(context: &mut Context, old: PathBuf, new: PathBuf) -> R<()> {
+    for path in [&old, &new] {
+        if !path.exists() {
+            return Err(format!("path not found: {}\n", render_path(path)).into());
+        }
+    }
+    let kind = |path: &PathBuf| if path.is_dir() { "directory" } else { "file" };
+    if old.is_file() && new.is_file() {
+        file::diff::output(context, &old, &new)?;
+    } else if old.is_dir() && new.is_dir() {
+        directory::diff::output(context, &old, &new)?;
+    } else {
+        return Err(format!(
+            "cannot compare {} {} with {} {}\n",
+            kind(&old),
+            render_path(&old),
+            kind(&new),
+            render_path(&new)
+        )
+        .into());
+    }
     Ok(())
 }
 
 fn show_information(context: &mut Context, entry: PathBuf) -> R<()> {
     if !entry.exists() {
+        if let Some((file, format, member)) = archive::split_path(&entry) {
+            return show_archive(context, file, format, &member);
+        }
         return Err(format!("path not found: {}\n", render_path(entry)).into());
     }
     if entry.is_symlink() {
@@ -74,6 +105,8 @@ fn show_information(context: &mut Context, entry: PathBuf) -> R<()> {
         )?;
         write_separator(context)?;
         show_information(context, destination)?;
+    } else if let Some(format) = archive::Format::from_path(&entry).filter(|_| entry.is_file()) {
+        show_archive(context, entry, format, Path::new(""))?;
     } else if entry.is_file() {
         file::output(context, entry)?;
     } else if entry.is_dir() {
@@ -84,6 +117,35 @@ fn show_information(context: &mut Context, entry: PathBuf) -> R<()> {
     Ok(())
 }
 
+/// Shows the archive directory `member` with the directory view, or a
+/// member file with the file view.
+fn show_archive(
+    context: &mut Context,
+    file: PathBuf,
+    format: archive::Format,
+    member: &Path,
+) -> R<()> {
+    if !file.is_file() {
+        return Err(format!("path not found: {}\n", render_path(file)).into());
+    }
+    let members = archive::read(&file, format)?;
+    let is_directory = member.as_os_str().is_empty()
+        || members
+            .iter()
+            .any(|x| x.path == member && x.kind == archive::Kind::Directory);
+    if is_directory {
+        directory::output_archive(context, &file, format, &members, member)
+    } else {
+        let contents = archive::read_member(&file, format, member)?;
+        let path = PathBuf::from(format!(
+            "{}//{}",
+            file.to_string_lossy(),
+            member.to_string_lossy()
+        ));
+        file::output_archive_member(context, path, contents)
+    }
+}
+
 fn separator(terminal_width: Option<usize>) -> String {
     format!(
         "{}\n",
@@ -110,13 +172,24 @@ mod test {
     use std::path::Path;
     use tempdir::TempDir;
 
+    use std::process::Command;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// Tests change the working directory of the whole process, so they
+    /// must not run concurrently.
+    static WORKING_DIRECTORY: Mutex<()> = Mutex::new(());
+
     pub struct Setup {
         stdout: Cursor<Vec<u8>>,
         tempdir: TempDir,
         outer_directory: PathBuf,
+        _lock: MutexGuard<'static, ()>,
     }
 
     pub fn setup() -> R<Setup> {
+        let lock = WORKING_DIRECTORY
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
         let outer_directory = std::env::current_dir()?;
         let tempdir = TempDir::new("si-test")?;
         std::env::set_current_dir(tempdir.path())?;
@@ -124,6 +197,7 @@ mod test {
             stdout: Cursor::new(vec![]),
             tempdir,
             outer_directory,
+            _lock: lock,
         })
     }
 
@@ -132,11 +206,7 @@ mod test {
     impl Setup {
         pub fn run<S: Into<String>>(&mut self, args: Vec<S>) -> R<()> {
             let context = &mut Context {
-                args: vec![
-                    vec!["si".to_string()],
-                    args.into_iter().map(|x| x.into()).collect(),
-                ]
-                .concat(),
+                args: Args::parse(args.into_iter().map(|x| x.into()))?,
                 stdout: &mut self.stdout,
                 terminal_width: TEST_TERMINAL_WIDTH,
             };
@@ -149,6 +219,32 @@ mod test {
             self.tempdir.path()
         }
 
+        /// Runs `git` in the temporary directory with a fixed identity and
+        /// a fixed date.
+        pub fn git(&self, args: &[&str]) -> R<String> {
+            let output = Command::new("git")
+                .args(["-c", "init.defaultBranch=main"])
+                .args(args)
+                .current_dir(self.tempdir())
+                .env("GIT_AUTHOR_NAME", "Alice")
+                .env("GIT_AUTHOR_EMAIL", "alice@example.com")
+                .env("GIT_COMMITTER_NAME", "Alice")
+                .env("GIT_COMMITTER_EMAIL", "alice@example.com")
+                .env("GIT_AUTHOR_DATE", "2022-10-19T12:00:00Z")
+                .env("GIT_COMMITTER_DATE", "2022-10-19T12:00:00Z")
+                .env("GIT_CONFIG_NOSYSTEM", "1")
+                .output()?;
+            if !output.status.success() {
+                return Err(format!(
+                    "git {:?} failed:\n{}",
+                    args,
+                    String::from_utf8_lossy(&output.stderr)
+                )
+                .into());
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+
         pub fn stdout(&self) -> String {
             String::from_utf8_lossy(self.stdout.get_ref()).into_owned()
         }
@@ -170,9 +266,9 @@ mod test {
 
     pub fn get_line(output: String, line: usize) -> String {
         output
-            .split("\n")
+            .split('\n')
             .nth(line)
-            .expect(&format!("get_line: no {}th line in:\n{}", line, output))
+            .unwrap_or_else(|| panic!("get_line: no {}th line in:\n{}", line, output))
             .to_string()
     }
 
@@ -219,7 +315,10 @@ mod test {
             setup.get_section(0),
             "./bar is a symbolic link pointing to foo\nresolving to:\n"
         );
-        assert_eq!(setup.get_section(1), "file: ./foo, 3 bytes\n");
+        assert_eq!(
+            setup.get_section(1),
+            "file: ./foo, 3 bytes\ntype: text/plain, utf-8, no trailing newline\n"
+        );
         Ok(())
     }
 
@@ -234,7 +333,10 @@ mod test {
             setup.get_section(0),
             "./dir/bar is a symbolic link pointing to foo\nresolving to:\n"
         );
-        assert_eq!(setup.get_section(1), "file: ./dir/foo, 3 bytes\n");
+        assert_eq!(
+            setup.get_section(1),
+            "file: ./dir/foo, 3 bytes\ntype: text/plain, utf-8, no trailing newline\n"
+        );
         Ok(())
     }
 
@@ -249,7 +351,10 @@ mod test {
             setup.get_section(0),
             "./dir/bar is a symbolic link pointing to ../foo\nresolving to:\n"
         );
-        assert_eq!(setup.get_section(1), "file: ./foo, 3 bytes\n");
+        assert_eq!(
+            setup.get_section(1),
+            "file: ./foo, 3 bytes\ntype: text/plain, utf-8, no trailing newline\n"
+        );
         Ok(())
     }
 
@@ -2,9 +2,34 @@ use std::path::Path;
 
 pub fn render_path<P: AsRef<Path>>(path: P) -> String {
     let result = path.as_ref().to_string_lossy().into_owned();
-    if result.starts_with(|c| c == '/' || c == '.') {
+    if result.starts_with(['/', '.']) {
         result
     } else {
         format!("./{}", result)
     }
 }
+
+pub fn render_size(bytes: u64) -> String {
+    let units = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1}{}", size, units[unit])
+    } else {
+        format!("{:.0}{}", size, units[unit])
+    }
+}
+
+pub fn pluralize(count: usize, singular: &str, plural: &str) -> String {
+    match count {
+        1 => format!("{} {}", count, singular),
+        _ => format!("{} {}", count, plural),
+    }
+}